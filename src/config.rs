@@ -0,0 +1,818 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+
+use inotify::{Inotify, WatchMask};
+
+use serde::{Deserialize, Serialize};
+
+use crate::gap::{Gap, GapScale};
+use crate::grid::GridFill;
+use crate::master_stack::MainLocation;
+use crate::tree::SplitNode;
+use crate::{BSPLayout, BSPLayoutError, LayoutMode, SmartGaps, SplitDirection, StackSizing};
+
+/// Options that can be set in the config file. Every option is optional, so a file or section
+/// only needs to mention the options that it wants to change
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LayoutOptions {
+    /// The number of pixels to pad each inner edge of a window by default. Every gap can also be
+    /// written with a unit, such as `"10px"` or `"1%"` of the size of the output
+    pub inner_gap: Option<Gap>,
+
+    /// The number of pixels to pad the left inner edge of each window. Overrides `inner_gap`
+    pub ig_left: Option<Gap>,
+
+    /// The number of pixels to pad the right inner edge of each window. Overrides `inner_gap`
+    pub ig_right: Option<Gap>,
+
+    /// The number of pixels to pad the bottom inner edge of each window. Overrides `inner_gap`
+    pub ig_bottom: Option<Gap>,
+
+    /// The number of pixels to pad the top inner edge of each window. Overrides `inner_gap`
+    pub ig_top: Option<Gap>,
+
+    /// The default size of the gap between windows and the edge of the screen
+    pub outer_gap: Option<Gap>,
+
+    /// The number of pixels between the left screen edge and any windows. Overrides `outer_gap`
+    pub og_left: Option<Gap>,
+
+    /// The number of pixels between the right screen edge and any windows. Overrides `outer_gap`
+    pub og_right: Option<Gap>,
+
+    /// The number of pixels between the bottom screen edge and any windows. Overrides `outer_gap`
+    pub og_bottom: Option<Gap>,
+
+    /// The number of pixels between the top screen edge and any windows. Overrides `outer_gap`
+    pub og_top: Option<Gap>,
+
+    /// When the outer gaps are left out
+    pub smart_gaps: Option<SmartGaps>,
+
+    /// How much to scale every gap by for each number of views, such as `"1.0,0.75,0.5@6"`
+    pub gap_scale: Option<GapScale>,
+
+    /// The default percentage of available area that the primary window should occupy after any
+    /// split takes place
+    pub split_perc: Option<f32>,
+
+    /// The percentage of available area that the primary window should occupy after a horizontal
+    /// split. Overrides `split_perc`
+    pub hsplit_perc: Option<f32>,
+
+    /// The percentage of available area that the primary window should occupy after a vertical
+    /// split. Overrides `split_perc`
+    pub vsplit_perc: Option<f32>,
+
+    /// The percentages used by splits inside other splits of the same direction, one entry per
+    /// level. The last entry is used for every level below
+    pub deeper_split_perc: Option<Vec<f32>>,
+
+    /// The percentages used by horizontal splits inside other horizontal splits. Overrides
+    /// `deeper_split_perc`
+    pub deeper_hsplit_perc: Option<Vec<f32>>,
+
+    /// The percentages used by vertical splits inside other vertical splits. Overrides
+    /// `deeper_split_perc`
+    pub deeper_vsplit_perc: Option<Vec<f32>>,
+
+    /// How much of the difference between the split percentage and 0.5 is kept at every level of
+    /// splits, between 0.0 and 1.0
+    pub split_decay: Option<f32>,
+
+    /// The number of pixels that the primary side of the first horizontal split of the `bsp`
+    /// layout occupies, instead of `hsplit-perc`. 0 uses `hsplit-perc`
+    pub hsplit_px: Option<u32>,
+
+    /// The number of pixels that the primary side of the first vertical split of the `bsp` layout
+    /// occupies, instead of `vsplit-perc`. 0 uses `vsplit-perc`
+    pub vsplit_px: Option<u32>,
+
+    /// Whether the first split that's made should divide the screen horizontally
+    pub start_hsplit: Option<bool>,
+
+    /// Whether new views should be prepended to the stack instead of appended
+    pub reverse: Option<bool>,
+
+    /// Whether to weight each split by the number of views on either side, so every view gets
+    /// roughly the same area
+    pub equal_area: Option<bool>,
+
+    /// Whether splits alternate between horizontal and vertical, or follow the longest side of
+    /// each container
+    pub split_direction: Option<SplitDirection>,
+
+    /// How much longer the width of a container has to be than its height before it is split
+    /// vertically. Only used when `split_direction` is `longest-side`
+    pub split_bias: Option<f32>,
+
+    /// The algorithm used to place the views
+    pub layout: Option<LayoutMode>,
+
+    /// How many views are placed in the main area of the `master-stack` and `centered-master`
+    /// layouts
+    pub main_count: Option<u32>,
+
+    /// The percentage of space that the main area of the `master-stack` layout occupies, or the
+    /// width of the center column of the `centered-master` layout
+    pub main_ratio: Option<f32>,
+
+    /// Which side of the screen the main area of the `master-stack` layout is placed on
+    pub main_location: Option<MainLocation>,
+
+    /// How many columns the `grid` layout has. 0 fits the columns to the number of views
+    pub grid_cols: Option<u32>,
+
+    /// How many rows the `grid` layout has. 0 fits the rows to the number of views
+    pub grid_rows: Option<u32>,
+
+    /// What to do with the last row of the `grid` layout when there are not enough views to fill
+    /// it
+    pub grid_fill: Option<GridFill>,
+
+    /// How the space is shared between the views of the `columns` and `rows` layouts
+    pub stack_sizing: Option<StackSizing>,
+
+    /// The layouts that `--next-layout` and `--prev-layout` cycle through, in order
+    pub layout_cycle: Option<Vec<LayoutMode>>,
+
+    /// Format of the layout name that is reported to river and status bars
+    pub name_format: Option<String>,
+
+    /// Splits of the BSP layout that have their own ratio
+    pub split_tree: Option<SplitNode>,
+}
+
+impl From<&BSPLayout> for LayoutOptions {
+    /// Capture every field of `layout`, so applying the options to another layout makes it match
+    /// `layout`
+    fn from(layout: &BSPLayout) -> Self {
+        LayoutOptions {
+            inner_gap: None,
+            ig_left: Some(layout.ig_left),
+            ig_right: Some(layout.ig_right),
+            ig_bottom: Some(layout.ig_bottom),
+            ig_top: Some(layout.ig_top),
+            outer_gap: None,
+            og_left: Some(layout.og_left),
+            og_right: Some(layout.og_right),
+            og_bottom: Some(layout.og_bottom),
+            og_top: Some(layout.og_top),
+            smart_gaps: Some(layout.smart_gaps),
+            gap_scale: Some(layout.gap_scale.clone()),
+            split_perc: None,
+            hsplit_perc: Some(layout.hsplit_perc),
+            vsplit_perc: Some(layout.vsplit_perc),
+            deeper_split_perc: None,
+            deeper_hsplit_perc: Some(layout.deeper_hsplit_perc.clone()),
+            deeper_vsplit_perc: Some(layout.deeper_vsplit_perc.clone()),
+            split_decay: Some(layout.split_decay),
+            hsplit_px: Some(layout.hsplit_px),
+            vsplit_px: Some(layout.vsplit_px),
+            start_hsplit: Some(layout.start_hsplit),
+            reverse: Some(layout.reversed),
+            equal_area: Some(layout.equal_area),
+            split_direction: Some(layout.split_direction),
+            split_bias: Some(layout.split_bias),
+            layout: Some(layout.mode),
+            main_count: Some(layout.main_count),
+            main_ratio: Some(layout.main_ratio),
+            main_location: Some(layout.main_location),
+            grid_cols: Some(layout.grid_cols),
+            grid_rows: Some(layout.grid_rows),
+            grid_fill: Some(layout.grid_fill),
+            stack_sizing: Some(layout.stack_sizing),
+            layout_cycle: Some(layout.layout_cycle.clone()),
+            name_format: Some(layout.name_format.clone()),
+            // Leave the tree out of the state file until a split has its own ratio
+            split_tree: (layout.split_tree != SplitNode::default())
+                .then(|| layout.split_tree.clone()),
+        }
+    }
+}
+
+/// Get `option` if `other` is set as well
+fn if_set<T: Clone, U>(option: &Option<T>, other: &Option<U>) -> Option<T> {
+    option.clone().filter(|_| other.is_some())
+}
+
+/// Get `option` if it is not the same as `initial`
+fn if_changed<T: Clone + PartialEq>(option: &Option<T>, initial: &Option<T>) -> Option<T> {
+    option.clone().filter(|_| option != initial)
+}
+
+/// Get the options of a group, such as `inner_gap` and the gap of each side, that change a value
+/// the same group of other options sets. The option for every side matches each of the options
+/// for one side, and the other way round. The options for one side are also kept along with a
+/// kept option for every side, so that applying the latter does not undo them
+///
+/// # Arguments
+///
+/// * `group` - The option that sets every member of the group
+/// * `members` - The options for each member of the group
+/// * `other_group` - The option of the other options that sets every member of the group
+/// * `other_members` - The options of the other options for each member of the group
+///
+/// # Returns
+///
+/// Tuple containing the kept option for the group, followed by the kept options for its members
+fn group_if_set<T: Clone, const N: usize>(
+    group: &Option<T>,
+    members: [&Option<T>; N],
+    other_group: &Option<T>,
+    other_members: [&Option<T>; N],
+) -> (Option<T>, [Option<T>; N]) {
+    let group_matched =
+        group.is_some() && (other_group.is_some() || other_members.iter().any(|m| m.is_some()));
+    let members_matched: [bool; N] = std::array::from_fn(|i| {
+        members[i].is_some() && (other_members[i].is_some() || other_group.is_some())
+    });
+
+    let keep_group = group_matched || members_matched.contains(&true);
+    (
+        group.clone().filter(|_| keep_group),
+        std::array::from_fn(|i| {
+            members[i]
+                .clone()
+                .filter(|_| group_matched || members_matched[i])
+        }),
+    )
+}
+
+impl LayoutOptions {
+    /// Get the options of `self` that change a value `other` sets as well, such as the command
+    /// line options that a section of the config file would otherwise override. An option like
+    /// `inner_gap` matches the options for each side, such as `ig_left`, and the other way round
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The options to keep the matching options of `self` for
+    pub fn restricted_to(&self, other: &LayoutOptions) -> LayoutOptions {
+        let (inner_gap, [ig_left, ig_right, ig_bottom, ig_top]) = group_if_set(
+            &self.inner_gap,
+            [&self.ig_left, &self.ig_right, &self.ig_bottom, &self.ig_top],
+            &other.inner_gap,
+            [
+                &other.ig_left,
+                &other.ig_right,
+                &other.ig_bottom,
+                &other.ig_top,
+            ],
+        );
+        let (outer_gap, [og_left, og_right, og_bottom, og_top]) = group_if_set(
+            &self.outer_gap,
+            [&self.og_left, &self.og_right, &self.og_bottom, &self.og_top],
+            &other.outer_gap,
+            [
+                &other.og_left,
+                &other.og_right,
+                &other.og_bottom,
+                &other.og_top,
+            ],
+        );
+        let (split_perc, [hsplit_perc, vsplit_perc]) = group_if_set(
+            &self.split_perc,
+            [&self.hsplit_perc, &self.vsplit_perc],
+            &other.split_perc,
+            [&other.hsplit_perc, &other.vsplit_perc],
+        );
+        let (deeper_split_perc, [deeper_hsplit_perc, deeper_vsplit_perc]) = group_if_set(
+            &self.deeper_split_perc,
+            [&self.deeper_hsplit_perc, &self.deeper_vsplit_perc],
+            &other.deeper_split_perc,
+            [&other.deeper_hsplit_perc, &other.deeper_vsplit_perc],
+        );
+
+        LayoutOptions {
+            inner_gap,
+            ig_left,
+            ig_right,
+            ig_bottom,
+            ig_top,
+            outer_gap,
+            og_left,
+            og_right,
+            og_bottom,
+            og_top,
+            smart_gaps: if_set(&self.smart_gaps, &other.smart_gaps),
+            gap_scale: if_set(&self.gap_scale, &other.gap_scale),
+            split_perc,
+            hsplit_perc,
+            vsplit_perc,
+            deeper_split_perc,
+            deeper_hsplit_perc,
+            deeper_vsplit_perc,
+            split_decay: if_set(&self.split_decay, &other.split_decay),
+            hsplit_px: if_set(&self.hsplit_px, &other.hsplit_px),
+            vsplit_px: if_set(&self.vsplit_px, &other.vsplit_px),
+            start_hsplit: if_set(&self.start_hsplit, &other.start_hsplit),
+            reverse: if_set(&self.reverse, &other.reverse),
+            equal_area: if_set(&self.equal_area, &other.equal_area),
+            split_direction: if_set(&self.split_direction, &other.split_direction),
+            split_bias: if_set(&self.split_bias, &other.split_bias),
+            layout: if_set(&self.layout, &other.layout),
+            main_count: if_set(&self.main_count, &other.main_count),
+            main_ratio: if_set(&self.main_ratio, &other.main_ratio),
+            main_location: if_set(&self.main_location, &other.main_location),
+            grid_cols: if_set(&self.grid_cols, &other.grid_cols),
+            grid_rows: if_set(&self.grid_rows, &other.grid_rows),
+            grid_fill: if_set(&self.grid_fill, &other.grid_fill),
+            stack_sizing: if_set(&self.stack_sizing, &other.stack_sizing),
+            layout_cycle: if_set(&self.layout_cycle, &other.layout_cycle),
+            name_format: if_set(&self.name_format, &other.name_format),
+            split_tree: if_set(&self.split_tree, &other.split_tree),
+        }
+    }
+
+    /// Get the options of `self` that are not the same as in `initial`, such as the options that
+    /// user commands changed since a state was created. Both should be captured from a layout,
+    /// so each option is either set in both or in neither
+    ///
+    /// # Arguments
+    ///
+    /// * `initial` - The options to compare against
+    pub fn changed_from(&self, initial: &LayoutOptions) -> LayoutOptions {
+        LayoutOptions {
+            inner_gap: if_changed(&self.inner_gap, &initial.inner_gap),
+            ig_left: if_changed(&self.ig_left, &initial.ig_left),
+            ig_right: if_changed(&self.ig_right, &initial.ig_right),
+            ig_bottom: if_changed(&self.ig_bottom, &initial.ig_bottom),
+            ig_top: if_changed(&self.ig_top, &initial.ig_top),
+            outer_gap: if_changed(&self.outer_gap, &initial.outer_gap),
+            og_left: if_changed(&self.og_left, &initial.og_left),
+            og_right: if_changed(&self.og_right, &initial.og_right),
+            og_bottom: if_changed(&self.og_bottom, &initial.og_bottom),
+            og_top: if_changed(&self.og_top, &initial.og_top),
+            smart_gaps: if_changed(&self.smart_gaps, &initial.smart_gaps),
+            gap_scale: if_changed(&self.gap_scale, &initial.gap_scale),
+            split_perc: if_changed(&self.split_perc, &initial.split_perc),
+            hsplit_perc: if_changed(&self.hsplit_perc, &initial.hsplit_perc),
+            vsplit_perc: if_changed(&self.vsplit_perc, &initial.vsplit_perc),
+            deeper_split_perc: if_changed(&self.deeper_split_perc, &initial.deeper_split_perc),
+            deeper_hsplit_perc: if_changed(&self.deeper_hsplit_perc, &initial.deeper_hsplit_perc),
+            deeper_vsplit_perc: if_changed(&self.deeper_vsplit_perc, &initial.deeper_vsplit_perc),
+            split_decay: if_changed(&self.split_decay, &initial.split_decay),
+            hsplit_px: if_changed(&self.hsplit_px, &initial.hsplit_px),
+            vsplit_px: if_changed(&self.vsplit_px, &initial.vsplit_px),
+            start_hsplit: if_changed(&self.start_hsplit, &initial.start_hsplit),
+            reverse: if_changed(&self.reverse, &initial.reverse),
+            equal_area: if_changed(&self.equal_area, &initial.equal_area),
+            split_direction: if_changed(&self.split_direction, &initial.split_direction),
+            split_bias: if_changed(&self.split_bias, &initial.split_bias),
+            layout: if_changed(&self.layout, &initial.layout),
+            main_count: if_changed(&self.main_count, &initial.main_count),
+            main_ratio: if_changed(&self.main_ratio, &initial.main_ratio),
+            main_location: if_changed(&self.main_location, &initial.main_location),
+            grid_cols: if_changed(&self.grid_cols, &initial.grid_cols),
+            grid_rows: if_changed(&self.grid_rows, &initial.grid_rows),
+            grid_fill: if_changed(&self.grid_fill, &initial.grid_fill),
+            stack_sizing: if_changed(&self.stack_sizing, &initial.stack_sizing),
+            layout_cycle: if_changed(&self.layout_cycle, &initial.layout_cycle),
+            name_format: if_changed(&self.name_format, &initial.name_format),
+            // A tree that was reset is left out when it is captured, so save the empty tree
+            split_tree: (self.split_tree != initial.split_tree)
+                .then(|| self.split_tree.clone().unwrap_or_default()),
+        }
+    }
+
+    /// Assign every option that is set to the matching field of `layout`. The `inner_gap`,
+    /// `outer_gap`, `split_perc` and `deeper_split_perc` options are applied first so that the
+    /// more specific options can override them
+    ///
+    /// # Arguments
+    ///
+    /// * `layout` - The layout to change
+    pub fn apply(&self, layout: &mut BSPLayout) {
+        if let Some(g) = self.inner_gap {
+            layout.set_inner_gaps(g);
+        }
+        if let Some(g) = self.ig_left {
+            layout.ig_left = g;
+        }
+        if let Some(g) = self.ig_right {
+            layout.ig_right = g;
+        }
+        if let Some(g) = self.ig_bottom {
+            layout.ig_bottom = g;
+        }
+        if let Some(g) = self.ig_top {
+            layout.ig_top = g;
+        }
+
+        if let Some(g) = self.outer_gap {
+            layout.set_outer_gaps(g);
+        }
+        if let Some(g) = self.og_left {
+            layout.og_left = g;
+        }
+        if let Some(g) = self.og_right {
+            layout.og_right = g;
+        }
+        if let Some(g) = self.og_bottom {
+            layout.og_bottom = g;
+        }
+        if let Some(g) = self.og_top {
+            layout.og_top = g;
+        }
+        if let Some(g) = self.smart_gaps {
+            layout.smart_gaps = g;
+        }
+        if let Some(s) = &self.gap_scale {
+            layout.gap_scale = s.clone();
+        }
+
+        if let Some(p) = self.split_perc {
+            layout.hsplit_perc = p;
+            layout.vsplit_perc = p;
+        }
+        if let Some(p) = self.hsplit_perc {
+            layout.hsplit_perc = p;
+        }
+        if let Some(p) = self.vsplit_perc {
+            layout.vsplit_perc = p;
+        }
+        if let Some(p) = &self.deeper_split_perc {
+            layout.deeper_hsplit_perc = p.clone();
+            layout.deeper_vsplit_perc = p.clone();
+        }
+        if let Some(p) = &self.deeper_hsplit_perc {
+            layout.deeper_hsplit_perc = p.clone();
+        }
+        if let Some(p) = &self.deeper_vsplit_perc {
+            layout.deeper_vsplit_perc = p.clone();
+        }
+        if let Some(d) = self.split_decay {
+            layout.split_decay = d;
+        }
+        if let Some(px) = self.hsplit_px {
+            layout.hsplit_px = px;
+        }
+        if let Some(px) = self.vsplit_px {
+            layout.vsplit_px = px;
+        }
+
+        if let Some(h) = self.start_hsplit {
+            layout.start_hsplit = h;
+        }
+        if let Some(r) = self.reverse {
+            layout.reversed = r;
+        }
+        if let Some(e) = self.equal_area {
+            layout.equal_area = e;
+        }
+        if let Some(d) = self.split_direction {
+            layout.split_direction = d;
+        }
+        if let Some(b) = self.split_bias {
+            layout.split_bias = b;
+        }
+
+        if let Some(m) = self.layout {
+            layout.mode = m;
+        }
+        if let Some(c) = self.main_count {
+            layout.main_count = c;
+        }
+        if let Some(r) = self.main_ratio {
+            layout.main_ratio = r;
+        }
+        if let Some(l) = self.main_location {
+            layout.main_location = l;
+        }
+
+        if let Some(c) = self.grid_cols {
+            layout.grid_cols = c;
+        }
+        if let Some(r) = self.grid_rows {
+            layout.grid_rows = r;
+        }
+        if let Some(f) = self.grid_fill {
+            layout.grid_fill = f;
+        }
+
+        if let Some(s) = self.stack_sizing {
+            layout.stack_sizing = s;
+        }
+
+        if let Some(c) = &self.layout_cycle {
+            layout.layout_cycle = c.clone();
+        }
+
+        if let Some(f) = &self.name_format {
+            layout.name_format = f.clone();
+        }
+
+        if let Some(t) = &self.split_tree {
+            layout.split_tree = t.clone();
+        }
+    }
+
+    /// Check that every split percentage and the main ratio are > 0.0 and < 1.0, that the split
+    /// decay is between 0.0 and 1.0, and that the split bias is > 0.0
+    ///
+    /// # Errors
+    ///
+    /// Will return `BSPLayoutError::ConfigError` naming the first option that is out of range
+    pub fn validate(&self) -> Result<(), BSPLayoutError> {
+        let deeper = [
+            ("deeper-split-perc", &self.deeper_split_perc),
+            ("deeper-hsplit-perc", &self.deeper_hsplit_perc),
+            ("deeper-vsplit-perc", &self.deeper_vsplit_perc),
+        ]
+        .into_iter()
+        .flat_map(|(name, percs)| percs.iter().flatten().map(move |p| (name, Some(*p))));
+
+        for (name, perc) in [
+            ("split-perc", self.split_perc),
+            ("hsplit-perc", self.hsplit_perc),
+            ("vsplit-perc", self.vsplit_perc),
+            ("main-ratio", self.main_ratio),
+        ]
+        .into_iter()
+        .chain(deeper)
+        {
+            if let Some(p) = perc {
+                if p <= 0.0 || p >= 1.0 {
+                    return Err(BSPLayoutError::ConfigError(format!(
+                        "{} must be > 0.0 and less than 1.0, got {}",
+                        name, p
+                    )));
+                }
+            }
+        }
+
+        if let Some(d) = self.split_decay {
+            if !(0.0..=1.0).contains(&d) {
+                return Err(BSPLayoutError::ConfigError(format!(
+                    "split-decay must be between 0.0 and 1.0, got {}",
+                    d
+                )));
+            }
+        }
+
+        if let Some(b) = self.split_bias {
+            if b <= 0.0 {
+                return Err(BSPLayoutError::ConfigError(format!(
+                    "split-bias must be > 0.0, got {}",
+                    b
+                )));
+            }
+        }
+
+        if let Some(t) = &self.split_tree {
+            t.validate()?;
+        }
+
+        if self.layout_cycle.as_ref().is_some_and(|c| c.is_empty()) {
+            return Err(BSPLayoutError::ConfigError(
+                "layout-cycle must contain at least one layout".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Contents of the config file. Options at the top level of the file apply to every output and
+/// tag, while the `output` and `tags` tables hold options that only apply to one output name or
+/// tag bitmask
+///
+/// ```toml
+/// inner-gap = 5
+/// outer-gap = 10
+///
+/// [output.DP-2]
+/// split-perc = 0.6
+///
+/// [tags.256]
+/// start-hsplit = true
+/// ```
+#[derive(Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Config {
+    /// Options that apply everywhere
+    #[serde(flatten)]
+    pub defaults: LayoutOptions,
+
+    /// Options for a single output, keyed on the output name such as `eDP-1`
+    #[serde(default)]
+    pub output: HashMap<String, LayoutOptions>,
+
+    /// Options for a single set of tags, keyed on the tag bitmask. For example, `[tags.1]` only
+    /// applies to the first tag, and `[tags.3]` applies when the first two tags are focused
+    #[serde(default)]
+    pub tags: HashMap<u32, LayoutOptions>,
+
+    /// Top level keys that are not options. Only kept so they can be reported as errors, since
+    /// `deny_unknown_fields` does not work together with `flatten`
+    #[serde(flatten)]
+    unknown: HashMap<String, toml::Value>,
+}
+
+impl Config {
+    /// Get the path that the config file is read from when no `--config` option is passed
+    ///
+    /// # Returns
+    ///
+    /// `$XDG_CONFIG_HOME/river-bsp-layout/config.toml`, falling back to
+    /// `$HOME/.config/river-bsp-layout/config.toml`. `None` if neither variable is set
+    pub fn default_path() -> Option<PathBuf> {
+        let config_home = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+
+        Some(config_home.join("river-bsp-layout").join("config.toml"))
+    }
+
+    /// Parse the contents of a config file
+    ///
+    /// # Arguments
+    ///
+    /// * `contents` - TOML text of the config file
+    ///
+    /// # Errors
+    ///
+    /// Will return `BSPLayoutError::ConfigError` if the text is not valid TOML, contains an
+    /// unknown option, or sets a split percentage that is out of range
+    pub fn parse(contents: &str) -> Result<Config, BSPLayoutError> {
+        let config: Config =
+            toml::from_str(contents).map_err(|e| BSPLayoutError::ConfigError(e.to_string()))?;
+
+        if let Some(key) = config.unknown.keys().next() {
+            return Err(BSPLayoutError::ConfigError(format!(
+                "Unknown option `{}`",
+                key
+            )));
+        }
+        config.defaults.validate()?;
+        for options in config.output.values().chain(config.tags.values()) {
+            options.validate()?;
+        }
+
+        Ok(config)
+    }
+
+    /// Read and parse the config file at `path`
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Location of the config file
+    ///
+    /// # Errors
+    ///
+    /// Will return `BSPLayoutError::ConfigError` if the file cannot be read or parsed
+    pub fn from_file(path: &Path) -> Result<Config, BSPLayoutError> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            BSPLayoutError::ConfigError(format!("Could not read {}: {}", path.display(), e))
+        })?;
+
+        Config::parse(&contents)
+    }
+
+    /// Apply the top level options to the defaults of `layout`, and store the output and tag
+    /// options so they are applied whenever `layout` creates the state for that output or tag
+    ///
+    /// # Arguments
+    ///
+    /// * `layout` - The layout to configure
+    pub fn apply(&self, layout: &mut BSPLayout) {
+        self.defaults.apply(layout);
+        layout.output_options = self.output.clone();
+        layout.tag_options = self.tags.clone();
+    }
+}
+
+/// Everything needed to build a `BSPLayout` from the config file again while the layout is
+/// running, such as after `--reload`
+#[derive(Clone, Debug)]
+pub struct ConfigSource {
+    /// Location of the config file
+    pub path: PathBuf,
+
+    /// If `false`, a missing file is treated as an empty config instead of an error
+    pub required: bool,
+
+    /// Options that the file is applied on top of
+    pub base: LayoutOptions,
+
+    /// Options that are applied on top of the file, such as those passed on the command line
+    pub overrides: LayoutOptions,
+}
+
+impl ConfigSource {
+    /// Create a new `ConfigSource` that has not read its file yet
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Location of the config file
+    /// * `required` - If `false`, a missing file is treated as an empty config
+    /// * `base` - Options that the file is applied on top of
+    /// * `overrides` - Options that are applied on top of the file
+    pub fn new(
+        path: PathBuf,
+        required: bool,
+        base: LayoutOptions,
+        overrides: LayoutOptions,
+    ) -> ConfigSource {
+        ConfigSource {
+            path,
+            required,
+            base,
+            overrides,
+        }
+    }
+
+    /// Start a thread that calls `on_change` whenever the config file is written, replaced or
+    /// removed. The directory of the file is watched rather than the file itself, so editors
+    /// that save by replacing the file are noticed too. If the path is a symlink, the directory
+    /// of the file it points to is watched as well
+    ///
+    /// # Arguments
+    ///
+    /// * `on_change` - Called once for every batch of changes to the file
+    ///
+    /// # Errors
+    ///
+    /// Will return `BSPLayoutError::ConfigError` if the directory of the file cannot be watched
+    pub fn watch<F>(&self, mut on_change: F) -> Result<JoinHandle<()>, BSPLayoutError>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let watch_error = |path: &Path, e: std::io::Error| {
+            BSPLayoutError::ConfigError(format!("Could not watch {}: {}", path.display(), e))
+        };
+
+        let mut inotify = Inotify::init().map_err(|e| watch_error(&self.path, e))?;
+        let mut files = Vec::new();
+        let targets = std::iter::once(self.path.clone()).chain(std::fs::canonicalize(&self.path));
+        for path in targets {
+            let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+                continue;
+            };
+            let dir = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            };
+
+            let watch = inotify
+                .watches()
+                .add(
+                    dir,
+                    WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::DELETE,
+                )
+                .map_err(|e| watch_error(dir, e))?;
+            files.push((watch, name.to_os_string()));
+        }
+
+        Ok(std::thread::spawn(move || {
+            let mut buffer = [0; 4096];
+            loop {
+                let events = match inotify.read_events_blocking(&mut buffer) {
+                    Ok(events) => events,
+                    Err(e) => {
+                        eprintln!("Stopped watching the config file: {}", e);
+                        return;
+                    }
+                };
+
+                let mut changed = false;
+                for event in events {
+                    changed |= files.iter().any(|(watch, name)| {
+                        event.wd == *watch && event.name == Some(name.as_os_str())
+                    });
+                }
+                if changed {
+                    on_change();
+                }
+            }
+        }))
+    }
+
+    /// Read the config file and build a new layout from `base`, the file and `overrides`, in
+    /// that order. The returned layout keeps a copy of this source so it can be reloaded later
+    ///
+    /// # Errors
+    ///
+    /// Will return `BSPLayoutError::ConfigError` if the file cannot be read or parsed, or if the
+    /// resulting split percentages are out of range
+    pub fn build(&self) -> Result<BSPLayout, BSPLayoutError> {
+        let config = if self.required || self.path.exists() {
+            Config::from_file(&self.path)?
+        } else {
+            Config::default()
+        };
+
+        let mut layout = BSPLayout::new();
+        self.base.apply(&mut layout);
+        config.apply(&mut layout);
+        self.overrides.apply(&mut layout);
+        LayoutOptions::from(&layout).validate()?;
+
+        layout.config_source = Some(self.clone());
+        Ok(layout)
+    }
+}
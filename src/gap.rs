@@ -0,0 +1,357 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use river_layout_toolkit::Rectangle;
+use serde::{Deserialize, Serialize};
+
+/// The size of a gap, either in pixels or as a percentage of the size of the output. Written as
+/// `10` or `10px` for pixels, and `1%` for a percentage
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "GapValue", into = "GapValue")]
+pub enum Gap {
+    /// A fixed number of pixels
+    Px(u32),
+
+    /// A percentage (between 0.0 and 100.0) of the width of the output for the left and right
+    /// gaps, or of its height for the top and bottom gaps
+    Percent(f32),
+}
+
+/// Which side of a view or of the output a gap is on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum GapSide {
+    /// The gap on the left
+    Left,
+
+    /// The gap on the right
+    Right,
+
+    /// The gap at the bottom
+    Bottom,
+
+    /// The gap at the top
+    Top,
+}
+
+impl Default for Gap {
+    fn default() -> Self {
+        Gap::Px(0)
+    }
+}
+
+impl Gap {
+    /// Get the size of the gap in pixels
+    ///
+    /// # Arguments
+    ///
+    /// * `length` - The width of the output for a left or right gap, or its height for a top or
+    ///   bottom gap
+    pub fn pixels(&self, length: u32) -> u32 {
+        match self {
+            Gap::Px(p) => *p,
+            Gap::Percent(p) => (length as f32 * p / 100.0).round() as u32,
+        }
+    }
+
+    /// Get the gap grown or shrunk by `amount`, which must use the same unit as the gap. The
+    /// result is kept between 0 and 100%
+    ///
+    /// # Arguments
+    ///
+    /// * `amount` - How much to grow or shrink the gap by
+    /// * `grow` - Whether to grow the gap instead of shrinking it
+    ///
+    /// # Errors
+    ///
+    /// Will return a message if the gap is in pixels and `amount` is a percentage, or the other
+    /// way around
+    pub fn change(&self, amount: Gap, grow: bool) -> Result<Gap, String> {
+        match (self, amount) {
+            (Gap::Px(g), Gap::Px(a)) if grow => Ok(Gap::Px(g.saturating_add(a))),
+            (Gap::Px(g), Gap::Px(a)) => Ok(Gap::Px(g.saturating_sub(a))),
+            (Gap::Percent(g), Gap::Percent(a)) if grow => Ok(Gap::Percent((g + a).min(100.0))),
+            (Gap::Percent(g), Gap::Percent(a)) => Ok(Gap::Percent((g - a).max(0.0))),
+            _ => Err(format!(
+                "Cannot change the gap {} by {}, both must be in pixels or both a percentage",
+                self, amount
+            )),
+        }
+    }
+}
+
+impl FromStr for Gap {
+    type Err = String;
+
+    /// Parse a gap such as `10`, `10px` or `1%`
+    ///
+    /// # Errors
+    ///
+    /// Will return a message if the number cannot be parsed, or if a percentage is not between
+    /// 0 and 100
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(p) = s.strip_suffix('%') {
+            let p: f32 = p
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid gap percentage `{}`", s))?;
+            if !(0.0..=100.0).contains(&p) {
+                return Err(format!(
+                    "Gap percentages must be between 0 and 100, got {}",
+                    s
+                ));
+            }
+            return Ok(Gap::Percent(p));
+        }
+
+        s.strip_suffix("px")
+            .unwrap_or(s)
+            .trim()
+            .parse()
+            .map(Gap::Px)
+            .map_err(|_| format!("Invalid gap `{}`, expected a size like 10, 10px or 1%", s))
+    }
+}
+
+impl Display for Gap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Gap::Px(p) => write!(f, "{}", p),
+            Gap::Percent(p) => write!(f, "{}%", p),
+        }
+    }
+}
+
+/// How a gap is written in the config and state files. Pixels are plain integers so files from
+/// before gaps had units can still be read
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum GapValue {
+    Px(u32),
+    Text(String),
+}
+
+impl TryFrom<GapValue> for Gap {
+    type Error = String;
+
+    fn try_from(value: GapValue) -> Result<Self, Self::Error> {
+        match value {
+            GapValue::Px(p) => Ok(Gap::Px(p)),
+            GapValue::Text(s) => s.parse(),
+        }
+    }
+}
+
+impl From<Gap> for GapValue {
+    fn from(gap: Gap) -> Self {
+        match gap {
+            Gap::Px(p) => GapValue::Px(p),
+            Gap::Percent(_) => GapValue::Text(gap.to_string()),
+        }
+    }
+}
+
+/// How much to scale every gap by for each number of views, so the gaps can shrink as more views
+/// are opened. Written as a comma separated list of scales such as `1.0,0.75,0.5@6`: each scale
+/// is for one more view than the one before it, starting at 1 view, unless `@` gives its view
+/// count. Between two scales the gaps shrink evenly, and the last scale is kept for every view
+/// count after it
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct GapScale {
+    /// Each view count with a scale, and its scale, sorted by view count
+    points: Vec<(u32, f32)>,
+}
+
+impl GapScale {
+    /// Get how much to scale the gaps by. Is 1.0 if no scales were given
+    ///
+    /// # Arguments
+    ///
+    /// * `view_count` - How many views the layout has
+    pub fn factor(&self, view_count: u32) -> f32 {
+        let after = self
+            .points
+            .iter()
+            .position(|(count, _)| *count > view_count);
+        match after {
+            None => self.points.last().map_or(1.0, |(_, scale)| *scale),
+            Some(0) => self.points[0].1,
+            Some(i) => {
+                let (from_count, from_scale) = self.points[i - 1];
+                let (to_count, to_scale) = self.points[i];
+                let t = (view_count - from_count) as f32 / (to_count - from_count) as f32;
+                from_scale + (to_scale - from_scale) * t
+            }
+        }
+    }
+}
+
+impl FromStr for GapScale {
+    type Err = String;
+
+    /// Parse a gap scale such as `1.0,0.75,0.5@6`. An empty string leaves the gaps unscaled
+    ///
+    /// # Errors
+    ///
+    /// Will return a message if a scale cannot be parsed or is negative, or if the view counts do
+    /// not go up
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().is_empty() {
+            return Ok(GapScale::default());
+        }
+
+        let mut points: Vec<(u32, f32)> = Vec::new();
+        for part in s.split(',') {
+            let (scale, count) = match part.split_once('@') {
+                Some((scale, count)) => {
+                    let count = count
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("Invalid view count in gap scale `{}`", part))?;
+                    (scale, Some(count))
+                }
+                None => (part, None),
+            };
+            let scale: f32 = scale
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid gap scale `{}`", part))?;
+            if !scale.is_finite() || scale < 0.0 {
+                return Err(format!("Gap scales must be 0.0 or more, got {}", part));
+            }
+
+            let next = points.last().map_or(1, |(count, _)| count + 1);
+            let count = count.unwrap_or(next);
+            if count < next {
+                return Err(format!(
+                    "The view counts of a gap scale must go up, starting at 1, got {}",
+                    s
+                ));
+            }
+            points.push((count, scale));
+        }
+
+        Ok(GapScale { points })
+    }
+}
+
+impl Display for GapScale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut next = 1;
+        let parts: Vec<String> = self
+            .points
+            .iter()
+            .map(|(count, scale)| {
+                let part = if *count == next {
+                    scale.to_string()
+                } else {
+                    format!("{}@{}", scale, count)
+                };
+                next = count + 1;
+                part
+            })
+            .collect();
+        write!(f, "{}", parts.join(","))
+    }
+}
+
+impl TryFrom<String> for GapScale {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<GapScale> for String {
+    fn from(scale: GapScale) -> Self {
+        scale.to_string()
+    }
+}
+
+/// Every gap of a layout in pixels, as they are used while the layout is generated
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct PixelGaps {
+    pub(crate) ig_left: u32,
+    pub(crate) ig_right: u32,
+    pub(crate) ig_bottom: u32,
+    pub(crate) ig_top: u32,
+    pub(crate) og_left: u32,
+    pub(crate) og_right: u32,
+    pub(crate) og_bottom: u32,
+    pub(crate) og_top: u32,
+}
+
+impl PixelGaps {
+    /// Shrink the outer gaps of each axis until they leave at least 1 pixel of the output for the
+    /// views, keeping the size of the gaps on both sides in proportion
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - How many pixels wide the output is
+    /// * `height` - How many pixels tall the output is
+    pub(crate) fn fit_outer(&mut self, width: u32, height: u32) {
+        (self.og_left, self.og_right) = fit(self.og_left, self.og_right, width);
+        (self.og_top, self.og_bottom) = fit(self.og_top, self.og_bottom, height);
+    }
+}
+
+/// Shrink the gaps at the start and end of a span until they leave at least 1 pixel of it
+fn fit(start: u32, end: u32, length: u32) -> (u32, u32) {
+    let total = start as u64 + end as u64;
+    if total == 0 || total < length as u64 {
+        return (start, end);
+    }
+
+    let available = length.saturating_sub(1) as u64;
+    let start = (start as u64 * available / total) as u32;
+    (start, available as u32 - start)
+}
+
+/// Cut a span in two after `split` pixels, taking `near_sub` off the end of the part at the start
+/// of the span and `far_sub` off the start of the part at its end. The gaps shrink if they do not
+/// fit, so both parts keep at least 1 pixel and stay inside the span
+///
+/// # Arguments
+///
+/// * `length` - The length in pixels of the span
+/// * `split` - How many pixels of the span are before the cut
+/// * `near_sub` - The gap taken off the end of the part at the start of the span
+/// * `far_sub` - The gap taken off the start of the part at the end of the span
+///
+/// # Returns
+///
+/// Tuple containing the length of the part at the start of the span, followed by the offset and
+/// length of the part at its end
+pub(crate) fn cut_span(length: u32, split: u32, near_sub: u32, far_sub: u32) -> (u32, u32, u32) {
+    let length = length.max(1);
+    let near = split.saturating_sub(near_sub).clamp(1, length);
+    let far_offset = split.saturating_add(far_sub).min(length - 1);
+    (near, far_offset, length - far_offset)
+}
+
+/// Move and shrink a view until it is inside an area, keeping at least 1 pixel of it
+///
+/// # Arguments
+///
+/// * `view` - The view to keep inside the area
+/// * `origin_x` - The x position of the top left of the area
+/// * `origin_y` - The y position of the top left of the area
+/// * `width` - The width in pixels of the area. Must be > 0
+/// * `height` - The height in pixels of the area. Must be > 0
+pub(crate) fn fit_view(
+    view: &mut Rectangle,
+    origin_x: i32,
+    origin_y: i32,
+    width: u32,
+    height: u32,
+) {
+    let fit_span = |position: i32, size: u32, origin: i32, length: u32| {
+        let offset = (position as i64 - origin as i64).clamp(0, length as i64 - 1) as u32;
+        (origin + offset as i32, size.clamp(1, length - offset))
+    };
+    (view.x, view.width) = fit_span(view.x, view.width, origin_x, width);
+    (view.y, view.height) = fit_span(view.y, view.height, origin_y, height);
+}
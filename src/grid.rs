@@ -0,0 +1,124 @@
+use clap::ValueEnum;
+use river_layout_toolkit::GeneratedLayout;
+use serde::{Deserialize, Serialize};
+
+use crate::{BSPLayout, BSPLayoutError};
+
+/// What to do with the views in the last row of `LayoutMode::Grid` when there are not enough of
+/// them to fill it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GridFill {
+    /// Widen the views in the last row so they fill it
+    #[default]
+    Stretch,
+
+    /// Keep the views in the last row the same size as the others, and center them in the row
+    Center,
+}
+
+impl BSPLayout {
+    /// Get the number of columns and rows of the grid. A `grid_cols` or `grid_rows` of 0 is
+    /// computed from the view count. When there are more views than explicit columns and rows can
+    /// hold, rows are added until all of them fit. Explicit columns and rows are limited to the
+    /// view count, since the cells are allocated before they are filled
+    ///
+    /// # Arguments
+    ///
+    /// * `view_count` - How many views need to fit in the grid. Must be > 0
+    ///
+    /// # Returns
+    ///
+    /// Tuple containing the number of columns and the number of rows
+    fn grid_size(&self, view_count: u32) -> (u32, u32) {
+        let cols = self.grid_cols.min(view_count);
+        let rows = self.grid_rows.min(view_count);
+        match (cols, rows) {
+            (0, 0) => {
+                let cols = (view_count as f32).sqrt().ceil() as u32;
+                (cols, view_count.div_ceil(cols))
+            }
+            (cols, 0) => (cols, view_count.div_ceil(cols)),
+            (0, rows) => (view_count.div_ceil(rows), rows),
+            (cols, rows) => (cols, rows.max(view_count.div_ceil(cols))),
+        }
+    }
+
+    /// Divide the area into a grid of equally sized cells, filling it one row at a time
+    ///
+    /// # Arguments
+    ///
+    /// * `origin_x` - The x position of the top left of the space to be divided
+    /// * `origin_y` - The y position of the top left of the space to be divided
+    /// * `canvas_width` - The width in pixels of the area being divided
+    /// * `canvas_height` - The height in pixels of the area being divided
+    /// * `view_count` - How many views the function needs to make in total
+    ///
+    /// # Returns
+    ///
+    /// A `GeneratedLayout` with `view_count` cells
+    pub(crate) fn grid(
+        &self,
+        origin_x: i32,
+        origin_y: i32,
+        canvas_width: u32,
+        canvas_height: u32,
+        view_count: u32,
+    ) -> Result<GeneratedLayout, BSPLayoutError> {
+        let mut layout = GeneratedLayout {
+            layout_name: String::new(),
+            views: Vec::with_capacity(view_count as usize),
+        };
+        if view_count == 0 {
+            return Ok(layout);
+        }
+
+        let (cols, rows) = self.grid_size(view_count);
+        let mut views_remaining = view_count;
+        for row in self.stack(
+            origin_x,
+            origin_y,
+            canvas_width,
+            canvas_height,
+            &vec![1.0; rows as usize],
+            true,
+        ) {
+            if views_remaining == 0 {
+                break;
+            }
+
+            let count = views_remaining.min(cols);
+            let cell_count = match self.grid_fill {
+                GridFill::Stretch => count,
+                GridFill::Center => cols,
+            };
+            let mut cells = self.stack(
+                row.x,
+                row.y,
+                row.width,
+                row.height,
+                &vec![1.0; cell_count as usize],
+                false,
+            );
+            cells.truncate(count as usize);
+
+            if count < cols && self.grid_fill == GridFill::Center {
+                let left = cells.iter().map(|c| c.x).min().unwrap_or(row.x);
+                let right = cells
+                    .iter()
+                    .map(|c| c.x + c.width as i32)
+                    .max()
+                    .unwrap_or(row.x);
+                let offset = row.x + (row.width as i32 - (right - left)) / 2 - left;
+                for cell in cells.iter_mut() {
+                    cell.x += offset;
+                }
+            }
+
+            layout.views.append(&mut cells);
+            views_remaining -= count;
+        }
+
+        Ok(layout)
+    }
+}
@@ -1,8 +1,23 @@
+pub mod config;
+pub mod gap;
+pub mod grid;
+pub mod master_stack;
+mod spiral;
+pub mod state;
+pub mod tree;
 pub mod user_cmd;
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use config::{ConfigSource, LayoutOptions};
+use gap::{cut_span, fit_view, Gap, GapScale, GapSide, PixelGaps};
+use grid::GridFill;
+use master_stack::MainLocation;
 use river_layout_toolkit::{GeneratedLayout, Layout, Rectangle};
+use serde::{Deserialize, Serialize};
+use state::{SavedLayout, StateFile};
+use std::collections::HashMap;
 use std::fmt::Display;
+use tree::{Branch, Direction, Side, SplitNode};
 
 /// Wrapper for errors relating to the creation or operation of a `BSPLayout`
 #[non_exhaustive]
@@ -13,6 +28,12 @@ pub enum BSPLayoutError {
 
     /// Encountered when there a failure occurs when generating a layout
     LayoutError(String),
+
+    /// Encountered when the config file cannot be read or contains invalid options
+    ConfigError(String),
+
+    /// Encountered when the state file cannot be read or written
+    StateError(String),
 }
 
 impl Display for BSPLayoutError {
@@ -23,102 +44,1082 @@ impl Display for BSPLayoutError {
 
 impl std::error::Error for BSPLayoutError {}
 
+/// How the direction of each split in a `BSPLayout` is chosen
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SplitDirection {
+    /// Strictly alternate between horizontal and vertical splits, starting with the direction
+    /// given by `start_hsplit`
+    #[default]
+    Alternate,
+
+    /// Split each container across its longest side, so tall containers are split horizontally
+    /// and wide containers are split vertically
+    LongestSide,
+}
+
+/// How the space is shared between the views of `LayoutMode::Columns` and `LayoutMode::Rows`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StackSizing {
+    /// Every view gets the same amount of space
+    #[default]
+    Equal,
+
+    /// Every view gets the split percentage of the space left over by the views before it, so
+    /// each view is smaller than the one before
+    Geometric,
+}
+
+/// When the gaps are left out so the views can use the whole screen
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SmartGaps {
+    /// Always keep the gaps
+    #[default]
+    Off,
+
+    /// Leave out the outer gaps while there is only one view
+    Single,
+
+    /// Leave out the outer gaps while only one view is visible, which includes monocle, and the
+    /// inner gaps of the layouts that stack the views: monocle, master-stack, columns and rows
+    Stacked,
+}
+
+/// The algorithm used to place the views
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LayoutMode {
+    /// Recursively divide the screen in half
+    #[default]
+    Bsp,
+
+    /// Place `main_count` views in a main area and stack the rest next to it, like rivertile
+    MasterStack,
+
+    /// Give every view the whole screen, minus the outer gaps
+    Monocle,
+
+    /// Split the most recently created view for every new view, always towards the same corner
+    Dwindle,
+
+    /// Split the most recently created view for every new view, rotating around the corners
+    Spiral,
+
+    /// Place the views in a grid of equally sized cells
+    Grid,
+
+    /// Place `main_count` views in a column in the middle of the screen, and alternate the rest
+    /// between a stack on either side of it
+    CenteredMaster,
+
+    /// Place every view side by side
+    Columns,
+
+    /// Place every view on top of each other
+    Rows,
+}
+
+impl LayoutMode {
+    /// Whether the mode stacks the views on top of or next to each other instead of dividing
+    /// the screen between them, which is when `SmartGaps::Stacked` leaves out the inner gaps
+    pub fn stacks_views(&self) -> bool {
+        matches!(
+            self,
+            LayoutMode::Monocle | LayoutMode::MasterStack | LayoutMode::Columns | LayoutMode::Rows
+        )
+    }
+}
+
+impl Display for LayoutMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.to_possible_value() {
+            Some(value) => write!(f, "{}", value.get_name()),
+            None => write!(f, "{:?}", self),
+        }
+    }
+}
+
 /// Create a Binary Space Partitioned layout. Specifically, this layout recursively
-/// divides the screen in half. The split will alternate between vertical and horizontal
-/// based on which side of the container is longer. This will result in a grid like
-/// layout with more-or-less equal sized windows evenly distributed across the screen
+/// divides the screen in half. The split will either alternate between vertical and horizontal,
+/// or be chosen based on which side of the container is longer, depending on `split_direction`.
+/// This will result in a grid like layout with more-or-less equal sized windows evenly
+/// distributed across the screen
+///
+/// The public fields hold the startup defaults. The first time a command is sent for a set of
+/// tags on an output, that output and those tags get their own copy of these values, so commands
+/// sent while one output or set of tags is focused do not bleed into the others
+#[derive(Clone, Debug)]
 pub struct BSPLayout {
-    /// Number of pixels to put between the left inside edge of adjacent windows
-    pub ig_left: u32,
+    /// Gap to put between the left inside edge of adjacent windows
+    pub ig_left: Gap,
 
-    /// Number of pixels to put between the right inside edge of adjacent windows
-    pub ig_right: u32,
+    /// Gap to put between the right inside edge of adjacent windows
+    pub ig_right: Gap,
 
-    /// Number of pixels to put between the bottom inside edge of adjacent windows
-    pub ig_bottom: u32,
+    /// Gap to put between the bottom inside edge of adjacent windows
+    pub ig_bottom: Gap,
 
-    /// Number of pixels to put between the top inside edge of adjacent windows
-    pub ig_top: u32,
+    /// Gap to put between the top inside edge of adjacent windows
+    pub ig_top: Gap,
 
-    /// Number of pixels to put between the left screen edge and the adjacent windows
-    pub og_left: u32,
+    /// Gap to put between the left screen edge and the adjacent windows
+    pub og_left: Gap,
 
-    /// Number of pixels to put between the right screen edge and the adjacent windows
-    pub og_right: u32,
+    /// Gap to put between the right screen edge and the adjacent windows
+    pub og_right: Gap,
 
-    /// Number of pixels to put between the bottom screen edge and the adjacent windows
-    pub og_bottom: u32,
+    /// Gap to put between the bottom screen edge and the adjacent windows
+    pub og_bottom: Gap,
 
-    /// Number of pixels to put between the top screen edge and the adjacent windows
-    pub og_top: u32,
+    /// Gap to put between the top screen edge and the adjacent windows
+    pub og_top: Gap,
 
     /// The percentage (between 0.0 and 1.0) of space that should be occupied by the primary window
     /// when a horizontal split takes place
     pub hsplit_perc: f32,
 
-    /// The percentage (between 0.0 and 1.0) of space that should be occupied by the primary window
-    /// when a vertical split takes place
-    pub vsplit_perc: f32,
+    /// The percentage (between 0.0 and 1.0) of space that should be occupied by the primary window
+    /// when a vertical split takes place
+    pub vsplit_perc: f32,
+
+    /// The percentages used instead of `hsplit_perc` by horizontal splits that are inside other
+    /// horizontal splits. The first entry is used one level down, the second two levels down, and
+    /// the last entry is repeated for every level below that. If empty, `hsplit_perc` is used at
+    /// every level
+    pub deeper_hsplit_perc: Vec<f32>,
+
+    /// The percentages used instead of `vsplit_perc` by vertical splits that are inside other
+    /// vertical splits, in the same way as `deeper_hsplit_perc`
+    pub deeper_vsplit_perc: Vec<f32>,
+
+    /// How much of the difference between the split percentage and 0.5 is kept at every level of
+    /// splits of the same direction. 1.0 uses the same percentage at every level, and lower
+    /// values pull deeper splits towards an even split, so the first view stays the largest
+    /// without making the deepest views too small
+    pub split_decay: f32,
+
+    /// The number of pixels that the primary side of the first horizontal split of the BSP
+    /// layout occupies, instead of `hsplit_perc`. Clamped to the height of the split, and 0 uses
+    /// `hsplit_perc`
+    pub hsplit_px: u32,
+
+    /// The number of pixels that the primary side of the first vertical split of the BSP layout
+    /// occupies, instead of `vsplit_perc`. Clamped to the width of the split, and 0 uses
+    /// `vsplit_perc`
+    pub vsplit_px: u32,
+
+    /// Whether the first split should be horizontal or not. If true, then start by dividing the
+    /// screen in half from right to left. If false, then start by dividing the screen in half from
+    /// top to bottom
+    pub start_hsplit: bool,
+
+    /// If `true`, new views will be prepended to the list. Otherwise, new views will be appended.
+    pub reversed: bool,
+
+    /// If `true`, the split percentages are weighted by how many views end up on each side of a
+    /// split, so every view gets roughly the same area. `hsplit_perc` and `vsplit_perc` then bias
+    /// the split towards the primary side when above 0.5, or away from it when below 0.5
+    pub equal_area: bool,
+
+    /// Whether splits strictly alternate between horizontal and vertical, or follow the longest
+    /// side of each container
+    pub split_direction: SplitDirection,
+
+    /// Only used with `SplitDirection::LongestSide`. A container is split vertically when its
+    /// width is more than `split_bias` times its height, and horizontally otherwise. Values above
+    /// 1.0 favor horizontal splits, and values below 1.0 favor vertical splits
+    pub split_bias: f32,
+
+    /// When the outer gaps are left out
+    pub smart_gaps: SmartGaps,
+
+    /// How much to scale every gap by for each number of views
+    pub gap_scale: GapScale,
+
+    /// The algorithm used to place the views
+    pub mode: LayoutMode,
+
+    /// How many views are placed in the main area when `mode` is `LayoutMode::MasterStack` or
+    /// `LayoutMode::CenteredMaster`
+    pub main_count: u32,
+
+    /// The percentage (between 0.0 and 1.0) of space that the main area occupies when `mode` is
+    /// `LayoutMode::MasterStack`, or the width of the center column when it is
+    /// `LayoutMode::CenteredMaster`
+    pub main_ratio: f32,
+
+    /// Which side of the screen the main area is placed on when `mode` is
+    /// `LayoutMode::MasterStack`
+    pub main_location: MainLocation,
+
+    /// How many columns the grid has when `mode` is `LayoutMode::Grid`. 0 fits the columns to
+    /// the number of views
+    pub grid_cols: u32,
+
+    /// How many rows the grid has when `mode` is `LayoutMode::Grid`. 0 fits the rows to the
+    /// number of views
+    pub grid_rows: u32,
+
+    /// What to do with the last row of the grid when there are not enough views to fill it
+    pub grid_fill: GridFill,
+
+    /// How the space is shared between the views when `mode` is `LayoutMode::Columns` or
+    /// `LayoutMode::Rows`. `StackSizing::Geometric` uses `vsplit_perc` for columns and
+    /// `hsplit_perc` for rows
+    pub stack_sizing: StackSizing,
+
+    /// Splits of the BSP layout that have their own ratio
+    pub split_tree: SplitNode,
+
+    /// Format of the layout name that is reported to river and status bars. See
+    /// `BSPLayout::layout_name` for the placeholders it can contain
+    pub name_format: String,
+
+    /// The modes that `--next-layout` and `--prev-layout` cycle through, in order
+    pub layout_cycle: Vec<LayoutMode>,
+
+    /// The mode to go back to when monocle is toggled off
+    previous_mode: LayoutMode,
+
+    /// The smart gaps to go back to when they are toggled on
+    previous_smart_gaps: SmartGaps,
+
+    /// Whether the gaps are used at all. `--toggle-gaps` turns them off without changing their
+    /// sizes
+    pub gaps_enabled: bool,
+
+    /// The gaps in pixels of the layout that is being generated
+    gaps: PixelGaps,
+
+    /// Every field of this state when it was created, so only the changes that user commands
+    /// made since then are saved to the state file
+    initial: LayoutOptions,
+
+    /// Layout state for each output name and tag bitmask that a command has been sent for
+    states: HashMap<(String, u32), BSPLayout>,
+
+    /// Defaults of each output name that a command without tags has been sent for. The states of
+    /// that output are created from these instead of the defaults of every output
+    output_states: HashMap<String, BSPLayout>,
+
+    /// The view count, usable width and usable height of the last layout of each output name and
+    /// tag bitmask
+    last_layouts: HashMap<(String, u32), (u32, u32, u32)>,
+
+    /// Options from the config file that only apply to one output
+    output_options: HashMap<String, LayoutOptions>,
+
+    /// Options from the config file that only apply to one tag bitmask
+    tag_options: HashMap<u32, LayoutOptions>,
+
+    /// Where the config file is read from, if this layout was built from one
+    config_source: Option<ConfigSource>,
+
+    /// Where the state is saved after every user command, if it should be saved at all
+    state_file: Option<StateFile>,
+}
+
+impl Default for BSPLayout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BSPLayout {
+    /// Initialize a new instance of BSPLayout with inner gaps of 5 pixels and outer gaps of 10
+    /// pixels on each side, a split percent of 50%, and starting on a vertical split
+    ///
+    /// # Returns
+    ///
+    /// A new `BSPLayout`
+    pub fn new() -> BSPLayout {
+        BSPLayout {
+            ig_left: Gap::Px(5),
+            ig_right: Gap::Px(5),
+            ig_bottom: Gap::Px(5),
+            ig_top: Gap::Px(5),
+            og_left: Gap::Px(10),
+            og_right: Gap::Px(10),
+            og_top: Gap::Px(10),
+            og_bottom: Gap::Px(10),
+            hsplit_perc: 0.5,
+            vsplit_perc: 0.5,
+            deeper_hsplit_perc: Vec::new(),
+            deeper_vsplit_perc: Vec::new(),
+            split_decay: 1.0,
+            hsplit_px: 0,
+            vsplit_px: 0,
+            reversed: false,
+            start_hsplit: false,
+            equal_area: false,
+            split_direction: SplitDirection::Alternate,
+            split_bias: 1.0,
+            smart_gaps: SmartGaps::Off,
+            gap_scale: GapScale::default(),
+            mode: LayoutMode::Bsp,
+            main_count: 1,
+            main_ratio: 0.6,
+            main_location: MainLocation::Left,
+            grid_cols: 0,
+            grid_rows: 0,
+            grid_fill: GridFill::Stretch,
+            stack_sizing: StackSizing::Equal,
+            split_tree: SplitNode::default(),
+            name_format: "{mode}".to_string(),
+            layout_cycle: LayoutMode::value_variants().to_vec(),
+            previous_mode: LayoutMode::Bsp,
+            previous_smart_gaps: SmartGaps::Single,
+            gaps_enabled: true,
+            gaps: PixelGaps::default(),
+            initial: LayoutOptions::default(),
+            states: HashMap::new(),
+            output_states: HashMap::new(),
+            last_layouts: HashMap::new(),
+            output_options: HashMap::new(),
+            tag_options: HashMap::new(),
+            config_source: None,
+            state_file: None,
+        }
+    }
+
+    /// Get the layout state that is used when `tags` are focused on `output`
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - The name of the output, such as `eDP-1`
+    /// * `tags` - Int representing which tags are active based on which bit is toggled
+    ///
+    /// # Returns
+    ///
+    /// The state for `output` and `tags`, or `None` if no command has been sent for them yet, in
+    /// which case the defaults are used
+    pub fn state(&self, output: &str, tags: u32) -> Option<&BSPLayout> {
+        self.states.get(&(output.to_string(), tags))
+    }
+
+    /// Get the defaults that commands without tags set for `output`
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - The name of the output, such as `eDP-1`
+    ///
+    /// # Returns
+    ///
+    /// The defaults of `output`, or `None` if no command without tags has been sent for it yet,
+    /// in which case the defaults of every output are used
+    pub fn output_state(&self, output: &str) -> Option<&BSPLayout> {
+        self.output_states.get(output)
+    }
+
+    /// Get the defaults of `output`, creating them from the defaults of every output if no
+    /// command without tags has been sent for it before
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - The name of the output, such as `eDP-1`
+    fn output_state_mut(&mut self, output: &str) -> &mut BSPLayout {
+        if !self.output_states.contains_key(output) {
+            let state = self.new_output_state(output);
+            self.output_states.insert(output.to_string(), state);
+        }
+        self.output_states.get_mut(output).unwrap()
+    }
+
+    /// Get the layout state for `output` and `tags`, creating it from the defaults if this
+    /// combination has not been seen before
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - The name of the output, such as `eDP-1`
+    /// * `tags` - Int representing which tags are active based on which bit is toggled
+    fn state_mut(&mut self, output: &str, tags: u32) -> &mut BSPLayout {
+        let key = (output.to_string(), tags);
+        if !self.states.contains_key(&key) {
+            let state = self.new_state(output, tags);
+            self.states.insert(key.clone(), state);
+        }
+        self.states.get_mut(&key).unwrap()
+    }
+
+    /// Get a copy of the defaults without the states and config sections they hold
+    fn defaults(&mut self) -> BSPLayout {
+        // Take the maps out first so the copy does not get them
+        let states = std::mem::take(&mut self.states);
+        let output_states = std::mem::take(&mut self.output_states);
+        let last_layouts = std::mem::take(&mut self.last_layouts);
+        let output_options = std::mem::take(&mut self.output_options);
+        let tag_options = std::mem::take(&mut self.tag_options);
+
+        let defaults = self.clone();
+
+        self.states = states;
+        self.output_states = output_states;
+        self.last_layouts = last_layouts;
+        self.output_options = output_options;
+        self.tag_options = tag_options;
+        defaults
+    }
+
+    /// Apply the options of a section of the config file to a new state. Options passed on the
+    /// command line still take precedence over the ones in the section
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The options of the section, if the config file has one
+    /// * `state` - The state to change
+    fn apply_section(&self, options: Option<&LayoutOptions>, state: &mut BSPLayout) {
+        let Some(options) = options else {
+            return;
+        };
+
+        options.apply(state);
+        if let Some(source) = &self.config_source {
+            source.overrides.restricted_to(options).apply(state);
+        }
+    }
+
+    /// Create the defaults of `output` from the defaults of every output, then apply any options
+    /// the config file gives for `output`
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - The name of the output, such as `eDP-1`
+    fn new_output_state(&mut self, output: &str) -> BSPLayout {
+        let mut state = self.defaults();
+        self.apply_section(self.output_options.get(output), &mut state);
+        state.initial = LayoutOptions::from(&state);
+        state
+    }
+
+    /// Create a state from the defaults of `output`, then apply any options the config file
+    /// gives for `tags`
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - The name of the output, such as `eDP-1`
+    /// * `tags` - Int representing which tags are active based on which bit is toggled
+    fn new_state(&mut self, output: &str, tags: u32) -> BSPLayout {
+        let mut state = match self.output_states.get(output) {
+            Some(defaults) => defaults.clone(),
+            None => self.new_output_state(output),
+        };
+        self.apply_section(self.tag_options.get(&tags), &mut state);
+        state.initial = LayoutOptions::from(&state);
+        state
+    }
+
+    /// Read the config file again and rebuild the defaults and every state from the result. The
+    /// changes that user commands made to each output and tag are applied on top of the new
+    /// states, so only the options that were not changed at runtime follow the file. If the file
+    /// cannot be read or parsed, the current state is kept
+    ///
+    /// # Errors
+    ///
+    /// Will return `BSPLayoutError::CmdError` if this layout was not built from a config file, or
+    /// `BSPLayoutError::ConfigError` if the file cannot be read or parsed
+    pub fn reload_config(&mut self) -> Result<(), BSPLayoutError> {
+        let source = match self.config_source.as_ref() {
+            Some(s) => s,
+            None => {
+                return Err(BSPLayoutError::CmdError(
+                    "There is no config file to reload".to_string(),
+                ))
+            }
+        };
+
+        let mut layout = source.build()?;
+        layout.state_file = self.state_file.take();
+        layout.last_layouts = std::mem::take(&mut self.last_layouts);
+        SavedLayout::from(&*self).apply(&mut layout);
+        *self = layout;
+        Ok(())
+    }
+
+    /// Save the defaults and every state to `state_file` after every successful user command
+    ///
+    /// # Arguments
+    ///
+    /// * `state_file` - Where to save the state. Use `StateFile::restore` to load it again
+    pub fn persist_to(&mut self, state_file: StateFile) {
+        self.state_file = Some(state_file);
+    }
+
+    /// Whether the config file has options that only apply to `output` or `tags`
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - The name of the output, such as `eDP-1`
+    /// * `tags` - Int representing which tags are active based on which bit is toggled
+    fn has_options_for(&self, output: &str, tags: u32) -> bool {
+        self.output_options.contains_key(output) || self.tag_options.contains_key(&tags)
+    }
+
+    /// Apply every option of a parsed user command to this layout
+    ///
+    /// # Arguments
+    ///
+    /// * `cmd` - The parsed command passed to `user_cmd`
+    ///
+    /// # Errors
+    ///
+    /// Will return `BSPLayoutError::CmdError` if the combination of options is invalid
+    fn apply_cmd(&mut self, cmd: &user_cmd::UserCmd) -> Result<(), BSPLayoutError> {
+        cmd.handle_outer_gaps(self);
+        cmd.handle_inner_gaps(self);
+        cmd.handle_ch_gaps(self)?;
+        cmd.handle_toggle_gaps(self);
+        cmd.handle_smart_gaps(self);
+        cmd.handle_gap_scale(self);
+        cmd.handle_start_split(self)?;
+        cmd.handle_set_split(self)?;
+        cmd.handle_ch_split(self);
+        cmd.handle_split_decay(self)?;
+        cmd.handle_split_px(self)?;
+        cmd.handle_reverse(self);
+        cmd.handle_equal_area(self);
+        cmd.handle_split_direction(self)?;
+        cmd.handle_layout(self);
+        cmd.handle_main(self)?;
+        cmd.handle_grid(self);
+        cmd.handle_stack_sizing(self);
+        cmd.handle_name_format(self);
+        cmd.handle_node(self)?;
+        cmd.handle_grow_view(self)?;
+
+        Ok(())
+    }
+
+    /// Create the geometry for the whole usable area of an output using this layout's parameters.
+    /// The gaps are turned into pixels for this output first, and the outer gaps are shrunk if
+    /// they would not leave any space for the views. Every view is then kept inside the area
+    /// within the outer gaps. Without any views, the layout is empty
+    ///
+    /// # Arguments
+    ///
+    /// * `view_count` - The number of views / windows / containers to divide the screen into
+    /// * `usable_width` - How many pixels wide the whole display is
+    /// * `usable_height` - How many pixels tall the whole display is
+    ///
+    /// # Errors
+    ///
+    /// Will return `BSPLayoutError::LayoutError` if the split percentages are out of range
+    fn layout_area(
+        &mut self,
+        view_count: u32,
+        usable_width: u32,
+        usable_height: u32,
+    ) -> Result<GeneratedLayout, BSPLayoutError> {
+        if view_count == 0 {
+            return Ok(GeneratedLayout {
+                layout_name: self.layout_name(view_count),
+                views: Vec::new(),
+            });
+        }
+
+        let (origin_x, origin_y, canvas_width, canvas_height) =
+            self.canvas(view_count, usable_width, usable_height);
+
+        let mut layout = match self.mode {
+            LayoutMode::Bsp => self.split(
+                origin_x,
+                origin_y,
+                canvas_width,
+                canvas_height,
+                view_count,
+                self.start_hsplit,
+                Branch::root(&self.split_tree),
+            ),
+            LayoutMode::MasterStack => {
+                self.master_stack(origin_x, origin_y, canvas_width, canvas_height, view_count)
+            }
+            LayoutMode::Monocle => Ok(GeneratedLayout {
+                layout_name: String::new(),
+                views: (0..view_count)
+                    .map(|_| Rectangle {
+                        x: origin_x,
+                        y: origin_y,
+                        width: canvas_width,
+                        height: canvas_height,
+                    })
+                    .collect(),
+            }),
+            LayoutMode::Dwindle => self.spiral(
+                origin_x,
+                origin_y,
+                canvas_width,
+                canvas_height,
+                view_count,
+                false,
+            ),
+            LayoutMode::Spiral => self.spiral(
+                origin_x,
+                origin_y,
+                canvas_width,
+                canvas_height,
+                view_count,
+                true,
+            ),
+            LayoutMode::Grid => {
+                self.grid(origin_x, origin_y, canvas_width, canvas_height, view_count)
+            }
+            LayoutMode::CenteredMaster => {
+                self.centered_master(origin_x, origin_y, canvas_width, canvas_height, view_count)
+            }
+            LayoutMode::Columns => self.line(
+                origin_x,
+                origin_y,
+                canvas_width,
+                canvas_height,
+                view_count,
+                false,
+            ),
+            LayoutMode::Rows => self.line(
+                origin_x,
+                origin_y,
+                canvas_width,
+                canvas_height,
+                view_count,
+                true,
+            ),
+        }?;
+
+        // Whatever the gaps and split percentages are, no view may end up off the output
+        for view in layout.views.iter_mut() {
+            fit_view(view, origin_x, origin_y, canvas_width, canvas_height);
+        }
+        // Every mode leaves the name empty, since it comes from the state rather than the mode
+        layout.layout_name = self.layout_name(view_count);
+        Ok(layout)
+    }
+
+    /// Turn the gaps into pixels for an output, shrinking the outer gaps if they would not leave
+    /// any space for the views, and find the area inside the outer gaps
+    ///
+    /// # Arguments
+    ///
+    /// * `view_count` - The number of views the layout is made for
+    /// * `usable_width` - How many pixels wide the whole display is
+    /// * `usable_height` - How many pixels tall the whole display is
+    ///
+    /// # Returns
+    ///
+    /// Tuple containing the x and y position of the top left of the area, followed by its width
+    /// and height, which are at least 1 pixel
+    pub(crate) fn canvas(
+        &mut self,
+        view_count: u32,
+        usable_width: u32,
+        usable_height: u32,
+    ) -> (i32, i32, u32, u32) {
+        let (drop_outer, drop_inner) = match self.smart_gaps {
+            _ if !self.gaps_enabled => (true, true),
+            SmartGaps::Off => (false, false),
+            SmartGaps::Single => (view_count == 1, false),
+            SmartGaps::Stacked => (
+                view_count == 1 || self.mode == LayoutMode::Monocle,
+                self.mode.stacks_views(),
+            ),
+        };
+        let scale = self.gap_scale.factor(view_count);
+        let scaled = |gap: Gap, length| (gap.pixels(length) as f32 * scale).round() as u32;
+        let inner = |gap: Gap, length| if drop_inner { 0 } else { scaled(gap, length) };
+        let outer = |gap: Gap, length| if drop_outer { 0 } else { scaled(gap, length) };
+        self.gaps = PixelGaps {
+            ig_left: inner(self.ig_left, usable_width),
+            ig_right: inner(self.ig_right, usable_width),
+            ig_bottom: inner(self.ig_bottom, usable_height),
+            ig_top: inner(self.ig_top, usable_height),
+            og_left: outer(self.og_left, usable_width),
+            og_right: outer(self.og_right, usable_width),
+            og_bottom: outer(self.og_bottom, usable_height),
+            og_top: outer(self.og_top, usable_height),
+        };
+        self.gaps.fit_outer(usable_width, usable_height);
+
+        (
+            self.gaps.og_left as i32,
+            self.gaps.og_top as i32,
+            (usable_width - self.gaps.og_left - self.gaps.og_right).max(1),
+            (usable_height - self.gaps.og_top - self.gaps.og_bottom).max(1),
+        )
+    }
+
+    /// Get the ratio that the split at `path` of the BSP layout uses for a layout, the way
+    /// `split_tree` stores it, and whether the split is horizontal. The split direction and the
+    /// percentage are worked out the same way as when the layout is made
+    ///
+    /// # Arguments
+    ///
+    /// * `view_count` - How many views the layout has
+    /// * `usable_width` - How many pixels wide the whole display is
+    /// * `usable_height` - How many pixels tall the whole display is
+    /// * `path` - Which side to take at every level, starting from the root split
+    ///
+    /// # Returns
+    ///
+    /// Tuple containing whether the split is horizontal and its ratio, or `None` if there is no
+    /// split at `path` with this many views
+    pub fn split_ratio(
+        &mut self,
+        view_count: u32,
+        usable_width: u32,
+        usable_height: u32,
+        path: &[Side],
+    ) -> Option<(bool, f32)> {
+        let (_, _, mut width, mut height) = self.canvas(view_count, usable_width, usable_height);
+        let (mut count, mut hsplit) = (view_count, self.start_hsplit);
+        let mut branch = Branch::root(&self.split_tree);
+        let mut path = path.iter();
+        while count >= 2 {
+            let hsplit_here = self.is_hsplit(hsplit, width, height);
+            let length = if hsplit_here { height } else { width };
+            let Some(side) = path.next() else {
+                let perc = self.prime_perc(hsplit_here, branch, length, count / 2, count);
+                return Some((hsplit_here, perc));
+            };
+
+            let (prime, sec) = self.split_parts(hsplit_here, branch, length, count);
+            let part = match side {
+                Side::Primary => {
+                    count /= 2;
+                    prime.1
+                }
+                Side::Secondary => {
+                    count -= count / 2;
+                    sec.1
+                }
+            };
+            if hsplit_here {
+                height = part;
+            } else {
+                width = part;
+            }
+            branch = branch.child(*side, hsplit_here);
+            hsplit = !hsplit_here;
+        }
+
+        None
+    }
+
+    /// Get how many pixels the primary side of the first split of one direction of the BSP layout
+    /// takes up, which is the split that `hsplit_px` or `vsplit_px` sizes. The split is found by
+    /// following the primary side from the root split, and its length is worked out the same way
+    /// as when the layout is made
+    ///
+    /// # Arguments
+    ///
+    /// * `hsplit` - Whether to look for a horizontal split
+    /// * `view_count` - How many views the layout has
+    /// * `usable_width` - How many pixels wide the whole display is
+    /// * `usable_height` - How many pixels tall the whole display is
+    ///
+    /// # Returns
+    ///
+    /// The length of the primary side of the split. If there is no such split with this many
+    /// views, the length it would have across the whole layout
+    pub fn first_split_length(
+        &mut self,
+        hsplit: bool,
+        view_count: u32,
+        usable_width: u32,
+        usable_height: u32,
+    ) -> u32 {
+        let (_, _, mut width, mut height) = self.canvas(view_count, usable_width, usable_height);
+        let (canvas_width, canvas_height) = (width, height);
+        let (mut count, mut next_hsplit) = (view_count, self.start_hsplit);
+        let mut branch = Branch::root(&self.split_tree);
+        while count >= 2 {
+            let hsplit_here = self.is_hsplit(next_hsplit, width, height);
+            let length = if hsplit_here { height } else { width };
+            if hsplit_here == hsplit {
+                return self.prime_length(hsplit_here, branch, length, count / 2, count);
+            }
+
+            let (prime, _) = self.split_parts(hsplit_here, branch, length, count);
+            if hsplit_here {
+                height = prime.1;
+            } else {
+                width = prime.1;
+            }
+            count /= 2;
+            branch = branch.child(Side::Primary, hsplit_here);
+            next_hsplit = !hsplit_here;
+        }
+
+        let length = if hsplit { canvas_height } else { canvas_width };
+        (length as f32 * self.depth_perc(hsplit, 0)) as u32
+    }
+
+    /// Render `name_format` into the name of a layout. The following placeholders are replaced:
+    ///
+    /// * `{mode}` - The active mode, such as `bsp` or `master-stack`
+    /// * `{count}` - The number of views
+    /// * `{hsplit}` - `hsplit_perc` as a whole percentage, such as `60`
+    /// * `{vsplit}` - `vsplit_perc` as a whole percentage
+    /// * `{reversed}` - `⇄` if the layout is reversed, and nothing otherwise
+    /// * `{start}` - `h` if the first split is horizontal, and `v` otherwise
+    /// * `{monocle}` - `[n]` with the number of views stacked on top of each other in monocle
+    ///   mode, and nothing otherwise. River does not tell layouts which view is focused, so the
+    ///   index of the visible view cannot be shown
+    ///
+    /// # Arguments
+    ///
+    /// * `view_count` - The number of views in the layout
+    ///
+    /// # Returns
+    ///
+    /// The rendered name, with surrounding whitespace removed
+    pub fn layout_name(&self, view_count: u32) -> String {
+        let monocle = if self.mode == LayoutMode::Monocle {
+            format!("[{}]", view_count)
+        } else {
+            String::new()
+        };
+
+        self.name_format
+            .replace("{mode}", &self.mode.to_string())
+            .replace("{count}", &view_count.to_string())
+            .replace(
+                "{hsplit}",
+                &((self.hsplit_perc * 100.0).round() as i32).to_string(),
+            )
+            .replace(
+                "{vsplit}",
+                &((self.vsplit_perc * 100.0).round() as i32).to_string(),
+            )
+            .replace("{reversed}", if self.reversed { "⇄" } else { "" })
+            .replace("{start}", if self.start_hsplit { "h" } else { "v" })
+            .replace("{monocle}", &monocle)
+            // River gets the name as a C string
+            .replace('\0', "")
+            .trim()
+            .to_string()
+    }
+
+    /// Switch to `LayoutMode::Monocle`, or back to the mode that was used before it if monocle is
+    /// already active
+    pub fn toggle_monocle(&mut self) {
+        if self.mode == LayoutMode::Monocle {
+            self.mode = self.previous_mode;
+        } else {
+            self.previous_mode = self.mode;
+            self.mode = LayoutMode::Monocle;
+        }
+    }
+
+    /// Turn `smart_gaps` off, or back to what it was before it was turned off
+    pub fn toggle_smart_gaps(&mut self) {
+        if self.smart_gaps == SmartGaps::Off {
+            self.smart_gaps = self.previous_smart_gaps;
+        } else {
+            self.previous_smart_gaps = self.smart_gaps;
+            self.smart_gaps = SmartGaps::Off;
+        }
+    }
+
+    /// Turn every gap off, or back on. The gaps keep their sizes while they are off
+    pub fn toggle_gaps(&mut self) {
+        self.gaps_enabled = !self.gaps_enabled;
+    }
+
+    /// Get the inner gap on one side of each view
+    ///
+    /// # Arguments
+    ///
+    /// * `side` - Which side of the views the gap is on
+    pub fn inner_gap_mut(&mut self, side: GapSide) -> &mut Gap {
+        match side {
+            GapSide::Left => &mut self.ig_left,
+            GapSide::Right => &mut self.ig_right,
+            GapSide::Bottom => &mut self.ig_bottom,
+            GapSide::Top => &mut self.ig_top,
+        }
+    }
+
+    /// Get the outer gap on one side of the output
+    ///
+    /// # Arguments
+    ///
+    /// * `side` - Which side of the output the gap is on
+    pub fn outer_gap_mut(&mut self, side: GapSide) -> &mut Gap {
+        match side {
+            GapSide::Left => &mut self.og_left,
+            GapSide::Right => &mut self.og_right,
+            GapSide::Bottom => &mut self.og_bottom,
+            GapSide::Top => &mut self.og_top,
+        }
+    }
+
+    /// Move the nearest split around the view at `index` that has the view on one side and
+    /// something in `direction` on the other, like bspwm resizes nodes. Only that split gets a
+    /// new ratio, every other split stays as it is
+    ///
+    /// # Arguments
+    ///
+    /// * `view_count` - How many views the layout has
+    /// * `usable_width` - How many pixels wide the whole display is
+    /// * `usable_height` - How many pixels tall the whole display is
+    /// * `index` - The position of the view in the layout, starting from 0
+    /// * `direction` - Which edge of the view to move
+    /// * `amount` - How much of its container the view grows by. Negative values shrink it
+    ///
+    /// # Errors
+    ///
+    /// Will return `BSPLayoutError::CmdError` if the layout is not `LayoutMode::Bsp`, or if no
+    /// split of the view has anything in `direction`
+    pub fn grow_view(
+        &mut self,
+        view_count: u32,
+        usable_width: u32,
+        usable_height: u32,
+        index: u32,
+        direction: Direction,
+        amount: f32,
+    ) -> Result<(), BSPLayoutError> {
+        if self.mode != LayoutMode::Bsp {
+            return Err(BSPLayoutError::CmdError(
+                "Views can only be resized in the bsp layout".to_string(),
+            ));
+        }
+        let views = self
+            .layout_area(view_count, usable_width, usable_height)?
+            .views;
+
+        // Every split the view is in, from the root down, with the views on either side of it
+        let mut ancestors = Vec::new();
+        let (mut path, mut start, mut count) = (Vec::new(), 0, view_count);
+        while count >= 2 && index < view_count {
+            let half = count / 2;
+            let side = if index < start + half {
+                Side::Primary
+            } else {
+                Side::Secondary
+            };
+            ancestors.push((
+                path.clone(),
+                start..start + half,
+                start + half..start + count,
+                side,
+            ));
+
+            if side == Side::Primary {
+                count = half;
+            } else {
+                start += half;
+                count -= half;
+            }
+            path.push(side);
+        }
+
+        let corner = |range: std::ops::Range<u32>| {
+            let cells = &views[range.start as usize..range.end as usize];
+            (
+                cells.iter().map(|v| v.x).min().unwrap_or(0),
+                cells.iter().map(|v| v.y).min().unwrap_or(0),
+            )
+        };
+        for (path, primary, secondary, side) in ancestors.into_iter().rev() {
+            let (prime_corner, sec_corner) = (corner(primary), corner(secondary));
+            let (own, other) = match side {
+                Side::Primary => (prime_corner, sec_corner),
+                Side::Secondary => (sec_corner, prime_corner),
+            };
+            let found = match direction {
+                Direction::Left => other.0 < own.0,
+                Direction::Right => other.0 > own.0,
+                Direction::Up => other.1 < own.1,
+                Direction::Down => other.1 > own.1,
+            };
+            if !found {
+                continue;
+            }
 
-    /// Whether the first split should be horizontal or not. If true, then start by dividing the
-    /// screen in half from right to left. If false, then start by dividing the screen in half from
-    /// top to bottom
-    pub start_hsplit: bool,
+            let (_, ratio) = self
+                .split_ratio(view_count, usable_width, usable_height, &path)
+                .unwrap_or((true, 0.5));
+            let node = self.split_tree.get_mut(&path);
+            let ratio = ratio
+                + match side {
+                    Side::Primary => amount,
+                    Side::Secondary => -amount,
+                };
+            node.ratio = Some(ratio.clamp(0.0001, 0.9999));
+            return Ok(());
+        }
 
-    /// If `true`, new views will be prepended to the list. Otherwise, new views will be appended.
-    pub reversed: bool,
-}
+        Err(BSPLayoutError::CmdError(format!(
+            "View {} has no split to move towards {:?}",
+            index, direction
+        )))
+    }
 
-impl BSPLayout {
-    /// Initialize a new instance of BSPLayout with inner gaps of 5 pixels and outer gaps of 10
-    /// pixels on each side, a split percent of 50%, and starting on a vertical split
+    /// Switch to the mode after the active one in `layout_cycle`, wrapping around at the end. If
+    /// the active mode is not in the cycle, switch to the first mode in it
     ///
-    /// # Returns
+    /// # Arguments
     ///
-    /// A new `BSPLayout`
-    pub fn new() -> BSPLayout {
-        BSPLayout {
-            ig_left: 5,
-            ig_right: 5,
-            ig_bottom: 5,
-            ig_top: 5,
-            og_left: 10,
-            og_right: 10,
-            og_top: 10,
-            og_bottom: 10,
-            hsplit_perc: 0.5,
-            vsplit_perc: 0.5,
-            reversed: false,
-            start_hsplit: false,
+    /// * `forward` - If `false`, switch to the mode before the active one instead
+    pub fn cycle_layout(&mut self, forward: bool) {
+        let len = self.layout_cycle.len();
+        if len == 0 {
+            return;
         }
+
+        let next = match self.layout_cycle.iter().position(|m| *m == self.mode) {
+            Some(i) if forward => (i + 1) % len,
+            Some(i) => (i + len - 1) % len,
+            None if forward => 0,
+            None => len - 1,
+        };
+        self.mode = self.layout_cycle[next];
     }
 
-    /// Sets all sides of outer gap to `new_gap`
+    /// Sets all sides of outer gap to `new_gap` pixels
     ///
     /// # Arguments
     ///
     /// * `new_gap` - The value to assign for the gap on all outer edges
     pub fn set_all_outer_gaps(&mut self, new_gap: u32) {
+        self.set_outer_gaps(Gap::Px(new_gap));
+    }
+
+    /// Sets all inner gaps to `new_gap` pixels
+    ///
+    /// # Arguments
+    ///
+    /// * `new_gap` - The value to assign for the gap on all inner edges between windows
+    pub fn set_all_inner_gaps(&mut self, new_gap: u32) {
+        self.set_inner_gaps(Gap::Px(new_gap));
+    }
+
+    /// Sets all sides of outer gap to `new_gap`, which may be a percentage of the output
+    ///
+    /// # Arguments
+    ///
+    /// * `new_gap` - The value to assign for the gap on all outer edges
+    pub fn set_outer_gaps(&mut self, new_gap: Gap) {
         self.og_top = new_gap;
         self.og_bottom = new_gap;
         self.og_left = new_gap;
         self.og_right = new_gap;
     }
 
-    /// Sets all inner gaps to `new_gap`
+    /// Sets all inner gaps to `new_gap`, which may be a percentage of the output
     ///
     /// # Arguments
     ///
     /// * `new_gap` - The value to assign for the gap on all inner edges between windows
-    pub fn set_all_inner_gaps(&mut self, new_gap: u32) {
+    pub fn set_inner_gaps(&mut self, new_gap: Gap) {
         self.ig_top = new_gap;
         self.ig_left = new_gap;
         self.ig_right = new_gap;
         self.ig_bottom = new_gap;
     }
 
-    /// Shared setup between vsplit and hsplit functions. First checks that vsplit_perc and
-    /// hsplit_perc are in range, then creates the layout variable, and finally calculates how many
+    /// Shared setup between vsplit and hsplit functions. First checks that every split percentage
+    /// is in range, then creates the layout variable, and finally calculates how many
     /// views are in each half of the split
     ///
     /// # Arguments
@@ -134,17 +1135,18 @@ impl BSPLayout {
     ///
     /// If either split percentage is not > 0.0 and < 1.0, return `BSPLayoutError`
     fn setup_split(&self, view_count: u32) -> Result<(u32, u32, GeneratedLayout), BSPLayoutError> {
-        if self.vsplit_perc <= 0.0
-            || self.vsplit_perc >= 1.0
-            || self.hsplit_perc <= 0.0
-            || self.hsplit_perc >= 1.0
+        if [self.hsplit_perc, self.vsplit_perc]
+            .iter()
+            .chain(&self.deeper_hsplit_perc)
+            .chain(&self.deeper_vsplit_perc)
+            .any(|p| *p <= 0.0 || *p >= 1.0)
         {
             return Err(BSPLayoutError::LayoutError(
                 "Split percents must be > 0.0 and less than 1.0".to_string(),
             ));
         }
         let layout = GeneratedLayout {
-            layout_name: "bsp-layout".to_string(),
+            layout_name: String::new(),
             views: Vec::with_capacity(view_count as usize),
         };
 
@@ -154,31 +1156,492 @@ impl BSPLayout {
         Ok((half_view_count, views_remaining, layout))
     }
 
-    /// Divide the screen in two by splitting from right to left first, then subsequently from
-    /// top to bottom
+    /// Get the split percentage used by a split with `depth` splits of the same direction above
+    /// it, taking `deeper_hsplit_perc` or `deeper_vsplit_perc` and `split_decay` into account
+    ///
+    /// # Arguments
+    ///
+    /// * `hsplit` - Whether the split is horizontal
+    /// * `depth` - How many splits of the same direction the split is inside of
+    ///
+    /// # Returns
+    ///
+    /// `hsplit_perc` or `vsplit_perc` at depth 0, and the matching entry of the deeper list below
+    /// that, moved towards 0.5 once for every level
+    pub fn depth_perc(&self, hsplit: bool, depth: usize) -> f32 {
+        let (perc, deeper) = if hsplit {
+            (self.hsplit_perc, &self.deeper_hsplit_perc)
+        } else {
+            (self.vsplit_perc, &self.deeper_vsplit_perc)
+        };
+
+        let perc = match depth.checked_sub(1) {
+            Some(i) => deeper.get(i).or(deeper.last()).copied().unwrap_or(perc),
+            None => perc,
+        };
+        0.5 + (perc - 0.5) * self.split_decay.powi(depth as i32)
+    }
+
+    /// Get the percentage of a split that should go to the primary side. Unless `equal_area` is
+    /// set, this is just `perc`
+    ///
+    /// # Arguments
+    ///
+    /// * `perc` - Either `hsplit_perc` or `vsplit_perc`, depending on the split
+    /// * `prime_count` - How many views will be placed in the primary side of the split
+    /// * `view_count` - How many views are on both sides of the split
+    ///
+    /// # Returns
+    ///
+    /// The percentage (between 0.0 and 1.0) of the split that the primary side should occupy
+    fn split_perc(&self, perc: f32, prime_count: u32, view_count: u32) -> f32 {
+        if !self.equal_area {
+            return perc;
+        }
+
+        // Weight the share of the views on the primary side by `perc`, so that 0.5 keeps the
+        // areas equal and anything else leans the split towards one side
+        let weight = prime_count as f32 / view_count as f32;
+        let prime = weight * perc;
+        prime / (prime + (1.0 - weight) * (1.0 - perc))
+    }
+
+    /// Get how many pixels of a split the primary side should occupy. This is the ratio of the
+    /// split in `split_tree` if it has one, then `hsplit_px` or `vsplit_px` for the first split of
+    /// that direction of the BSP layout, and the split percentage otherwise
+    ///
+    /// # Arguments
+    ///
+    /// * `hsplit` - Whether the split is horizontal
+    /// * `branch` - Where the split is in the tree of splits
+    /// * `length` - The height of a horizontal split, or the width of a vertical split
+    /// * `prime_count` - How many views will be placed in the primary side of the split
+    /// * `view_count` - How many views are on both sides of the split
+    ///
+    /// # Returns
+    ///
+    /// The length of the primary side, which may still be as long as the whole split
+    fn prime_length(
+        &self,
+        hsplit: bool,
+        branch: Branch,
+        length: u32,
+        prime_count: u32,
+        view_count: u32,
+    ) -> u32 {
+        if let Some(px) = self.split_px(hsplit, branch) {
+            return px;
+        }
+        let perc = self.prime_perc(hsplit, branch, length, prime_count, view_count);
+
+        (length as f32 * self.split_perc(perc, prime_count, view_count)) as u32
+    }
+
+    /// Get the percentage of a split that `split_tree` would store for it, which is the ratio it
+    /// uses now. For a split sized by `hsplit_px` or `vsplit_px`, this is the percentage that
+    /// keeps the primary side the same size
+    ///
+    /// # Arguments
+    ///
+    /// * `hsplit` - Whether the split is horizontal
+    /// * `branch` - Where the split is in the tree of splits
+    /// * `length` - The height of a horizontal split, or the width of a vertical split
+    /// * `prime_count` - How many views will be placed in the primary side of the split
+    /// * `view_count` - How many views are on both sides of the split
+    fn prime_perc(
+        &self,
+        hsplit: bool,
+        branch: Branch,
+        length: u32,
+        prime_count: u32,
+        view_count: u32,
+    ) -> f32 {
+        match self.split_px(hsplit, branch) {
+            Some(px) => {
+                // Aim for the middle of the pixel, so rounding down still gives `px`
+                let length = length.max(2);
+                let r = (px.clamp(1, length - 1) as f32 + 0.5) / length as f32;
+                if !self.equal_area {
+                    return r;
+                }
+
+                // Undo the weighting of `split_perc`
+                let weight = prime_count as f32 / view_count as f32;
+                r * (1.0 - weight) / (weight * (1.0 - r) + r * (1.0 - weight))
+            }
+            None => branch
+                .ratio()
+                .unwrap_or_else(|| self.depth_perc(hsplit, branch.depth(hsplit))),
+        }
+    }
+
+    /// Get `hsplit_px` or `vsplit_px` if it sizes a split: only the first split of that
+    /// direction of the BSP layout uses it, and only if the split has no ratio of its own
+    ///
+    /// # Arguments
+    ///
+    /// * `hsplit` - Whether the split is horizontal
+    /// * `branch` - Where the split is in the tree of splits
+    fn split_px(&self, hsplit: bool, branch: Branch) -> Option<u32> {
+        let px = if hsplit {
+            self.hsplit_px
+        } else {
+            self.vsplit_px
+        };
+        (px > 0
+            && branch.ratio().is_none()
+            && branch.depth(hsplit) == 0
+            && self.mode == LayoutMode::Bsp)
+            .then_some(px)
+    }
+
+    /// Work out where both sides of a split of the BSP layout go along the axis it divides,
+    /// leaving the inner gaps between them
+    ///
+    /// # Arguments
+    ///
+    /// * `hsplit` - Whether the split is horizontal
+    /// * `branch` - Where the split is in the tree of splits
+    /// * `length` - The height of a horizontal split, or the width of a vertical split
+    /// * `view_count` - How many views are on both sides of the split
+    ///
+    /// # Returns
+    ///
+    /// Tuple containing the offset from the start of the split and the length of the primary
+    /// side, followed by the same for the secondary side
+    fn split_parts(
+        &self,
+        hsplit: bool,
+        branch: Branch,
+        length: u32,
+        view_count: u32,
+    ) -> ((u32, u32), (u32, u32)) {
+        let length = length.max(1);
+        let prime_split = self
+            .prime_length(hsplit, branch, length, view_count / 2, view_count)
+            .max(1)
+            .min(length - 1);
+        let sec_split = length - prime_split;
+
+        // Whichever side is at the start of the split has the bottom or right gap taken off its
+        // end, and the other side has the top or left gap taken off its start
+        let (near_sub, far_sub) = if hsplit {
+            (self.gaps.ig_bottom, self.gaps.ig_top)
+        } else {
+            (self.gaps.ig_right, self.gaps.ig_left)
+        };
+        let near_split = if !self.reversed {
+            prime_split
+        } else {
+            sec_split
+        };
+        let (near_length, far_offset, far_length) = cut_span(length, near_split, near_sub, far_sub);
+        let (near, far) = ((0, near_length), (far_offset, far_length));
+        if !self.reversed {
+            (near, far)
+        } else {
+            (far, near)
+        }
+    }
+
+    /// Pick the direction of a split of the BSP layout
+    ///
+    /// # Arguments
+    ///
+    /// * `hsplit` - Whether to split horizontally when the splits alternate
+    /// * `width` - The width in pixels of the area being divided
+    /// * `height` - The height in pixels of the area being divided
+    fn is_hsplit(&self, hsplit: bool, width: u32, height: u32) -> bool {
+        match self.split_direction {
+            SplitDirection::Alternate => hsplit,
+            SplitDirection::LongestSide => width as f32 <= height as f32 * self.split_bias,
+        }
+    }
+
+    /// Divide a span of pixels along one axis in two, leaving the inner gaps between both parts
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - The position of the start of the span
+    /// * `length` - The length in pixels of the span
+    /// * `perc` - The percentage (between 0.0 and 1.0) of the span for the primary part
+    /// * `swap` - If `true`, put the primary part at the end of the span instead of the start
+    /// * `near_sub` - The gap taken off the end of whichever part is at the start of the span
+    /// * `far_sub` - The gap taken off the start of whichever part is at the end of the span
+    ///
+    /// # Returns
+    ///
+    /// Tuple containing the position and length of the primary part, followed by the position
+    /// and length of the secondary part
+    fn divide(
+        &self,
+        origin: i32,
+        length: u32,
+        perc: f32,
+        swap: bool,
+        near_sub: u32,
+        far_sub: u32,
+    ) -> ((i32, u32), (i32, u32)) {
+        let prime_split = ((length as f32 * perc) as u32).clamp(1, length.max(2) - 1);
+        let near_split = if swap {
+            length.saturating_sub(prime_split)
+        } else {
+            prime_split
+        };
+
+        let (near_length, far_offset, far_length) = cut_span(length, near_split, near_sub, far_sub);
+        let near = (origin, near_length);
+        let far = (origin + far_offset as i32, far_length);
+
+        if swap {
+            (far, near)
+        } else {
+            (near, far)
+        }
+    }
+
+    /// Place every view in a single row or column, sized according to `stack_sizing`
+    ///
+    /// # Arguments
+    ///
+    /// * `origin_x` - The x position of the top left of the space to be divided
+    /// * `origin_y` - The y position of the top left of the space to be divided
+    /// * `canvas_width` - The width in pixels of the area being divided
+    /// * `canvas_height` - The height in pixels of the area being divided
+    /// * `view_count` - How many views the function needs to make in total
+    /// * `rows` - If `true`, stack the views on top of each other instead of side by side
+    ///
+    /// # Returns
+    ///
+    /// A `GeneratedLayout` with `view_count` cells
+    ///
+    /// # Errors
+    ///
+    /// Will return `BSPLayoutError::LayoutError` if either split percentage is not > 0.0 and
+    /// < 1.0
+    fn line(
+        &self,
+        origin_x: i32,
+        origin_y: i32,
+        canvas_width: u32,
+        canvas_height: u32,
+        view_count: u32,
+        rows: bool,
+    ) -> Result<GeneratedLayout, BSPLayoutError> {
+        let (_, _, mut layout) = self.setup_split(view_count)?;
+
+        let weights: Vec<f32> = match self.stack_sizing {
+            StackSizing::Equal => vec![1.0; view_count as usize],
+            StackSizing::Geometric => {
+                let perc = if rows {
+                    self.hsplit_perc
+                } else {
+                    self.vsplit_perc
+                };
+                // Each view takes `perc` of what is left, and the last view takes the rest
+                (0..view_count)
+                    .map(|i| {
+                        let rest = (1.0 - perc).powi(i as i32);
+                        if i + 1 == view_count {
+                            rest
+                        } else {
+                            rest * perc
+                        }
+                    })
+                    .collect()
+            }
+        };
+
+        layout.views = self.stack(
+            origin_x,
+            origin_y,
+            canvas_width,
+            canvas_height,
+            &weights,
+            rows,
+        );
+        Ok(layout)
+    }
+
+    /// Divide the area into rows or columns whose sizes are proportional to `weights`, leaving
+    /// the inner gaps between them. If `reversed` is set, the first view is placed at the end of
+    /// the area instead of the start
+    ///
+    /// # Arguments
+    ///
+    /// * `origin_x` - The x position of the top left of the space to be divided
+    /// * `origin_y` - The y position of the top left of the space to be divided
+    /// * `canvas_width` - The width in pixels of the area being divided
+    /// * `canvas_height` - The height in pixels of the area being divided
+    /// * `weights` - The relative size of each view, in the order of the views
+    /// * `rows` - If `true`, stack the views from top to bottom. Otherwise, place them side by
+    ///   side from left to right
+    ///
+    /// # Returns
+    ///
+    /// One `Rectangle` for every entry in `weights`
+    fn stack(
+        &self,
+        origin_x: i32,
+        origin_y: i32,
+        canvas_width: u32,
+        canvas_height: u32,
+        weights: &[f32],
+        rows: bool,
+    ) -> Vec<Rectangle> {
+        let (origin, length, near_sub, far_sub) = if rows {
+            (
+                origin_y,
+                canvas_height,
+                self.gaps.ig_bottom,
+                self.gaps.ig_top,
+            )
+        } else {
+            (
+                origin_x,
+                canvas_width,
+                self.gaps.ig_right,
+                self.gaps.ig_left,
+            )
+        };
+
+        let mut weights = weights.to_vec();
+        if self.reversed {
+            weights.reverse();
+        }
+        let total: f64 = weights.iter().map(|w| *w as f64).sum();
+
+        let mut views = Vec::with_capacity(weights.len());
+        let mut cumulative = 0.0;
+        let mut start = 0;
+        for (i, weight) in weights.iter().enumerate() {
+            cumulative += *weight as f64;
+            // Every view after this one keeps at least 1 pixel, as long as the area is long enough
+            let remaining = (weights.len() - i - 1) as u32;
+            let end = if remaining == 0 {
+                length
+            } else {
+                ((length as f64 * cumulative / total) as u32)
+                    .max(start + 1)
+                    .min(length.saturating_sub(remaining))
+                    .max(start)
+            };
+
+            // The gaps shrink if they do not fit, so every view keeps at least 1 pixel
+            let piece = end - start;
+            let far = if i > 0 {
+                far_sub.min(piece.saturating_sub(1))
+            } else {
+                0
+            };
+            let near = if i + 1 < weights.len() {
+                near_sub.min(piece.saturating_sub(far + 1))
+            } else {
+                0
+            };
+            let offset = (start + far).min(length - 1);
+            let position = origin + offset as i32;
+            let size = (piece - far - near).clamp(1, length - offset);
+            views.push(if rows {
+                Rectangle {
+                    x: origin_x,
+                    y: position,
+                    width: canvas_width,
+                    height: size,
+                }
+            } else {
+                Rectangle {
+                    x: position,
+                    y: origin_y,
+                    width: size,
+                    height: canvas_height,
+                }
+            });
+            start = end;
+        }
+
+        if self.reversed {
+            views.reverse();
+        }
+        views
+    }
+
+    /// Divide the area in two, choosing the direction of the split based on `split_direction`
+    ///
+    /// # Arguments
+    ///
+    /// * `origin_x` - The x position of the top left of the space to be divided
+    /// * `origin_y` - The y position of the top left of the space to be divided
+    /// * `canvas_width` - The width in pixels of the area being divided
+    /// * `canvas_height` - The height in pixels of the area being divided
+    /// * `view_count` - How many views the function needs to make in total
+    /// * `hsplit` - Whether to split horizontally when the splits alternate
+    /// * `branch` - Where this area is in the tree of splits
+    ///
+    /// # Returns
+    ///
+    /// A `GeneratedLayout` with `view_count` cells
+    #[allow(clippy::too_many_arguments)]
+    fn split(
+        &self,
+        origin_x: i32,
+        origin_y: i32,
+        canvas_width: u32,
+        canvas_height: u32,
+        view_count: u32,
+        hsplit: bool,
+        branch: Branch,
+    ) -> Result<GeneratedLayout, BSPLayoutError> {
+        if self.is_hsplit(hsplit, canvas_width, canvas_height) {
+            self.hsplit(
+                origin_x,
+                origin_y,
+                canvas_width,
+                canvas_height,
+                view_count,
+                branch,
+            )
+        } else {
+            self.vsplit(
+                origin_x,
+                origin_y,
+                canvas_width,
+                canvas_height,
+                view_count,
+                branch,
+            )
+        }
+    }
+
+    /// Divide the area in two by splitting from right to left, then divide each half with
+    /// `split`. The direction of the splits inside each half comes from `split_direction`: they
+    /// split from top to bottom when the splits alternate, or across the longest side of the half
     ///
     /// # Arguments
     ///
     /// * `origin_x` - The x position of the top left of the space to be divided
-    /// relative to the entire display. For example, if you are dividing the entire
-    /// display, then the top left corner is 0, 0. If you are dividing the right
-    /// half of a 1920x1080 monitor, then the top left corner would be at 960, 0
+    ///   relative to the entire display. For example, if you are dividing the entire
+    ///   display, then the top left corner is 0, 0. If you are dividing the right
+    ///   half of a 1920x1080 monitor, then the top left corner would be at 960, 0
     ///
     /// * `origin_y` - The y position of the top left of the space to be divided
-    /// relative to the entire display. For example, if you are dividing the entire
-    /// display, then the top left corner is 0, 0. If you are dividing the bottom
-    /// half of a 1920x1080 monitor, then the top left corner would be at 0, 540
+    ///   relative to the entire display. For example, if you are dividing the entire
+    ///   display, then the top left corner is 0, 0. If you are dividing the bottom
+    ///   half of a 1920x1080 monitor, then the top left corner would be at 0, 540
     ///
     /// * `canvas_width` - The width in pixels of the area being divided. If you
-    /// are dividing all of a 1920x1080 monitor, then the `canvas_width` would be 1920.
-    /// If you are dividing the right half of the monitor, then the width is 960.
+    ///   are dividing all of a 1920x1080 monitor, then the `canvas_width` would be 1920.
+    ///   If you are dividing the right half of the monitor, then the width is 960.
     ///
     /// * `canvas_height` - The height in pixels of the area being divided. If you
-    /// are dividing all of a 1920x1080 monitor, then the height would be 1080.
-    /// If you are dividing the bottom half of the monitor, then the height is 540.
+    ///   are dividing all of a 1920x1080 monitor, then the height would be 1080.
+    ///   If you are dividing the bottom half of the monitor, then the height is 540.
     ///
     /// * `view_count` - How many windows / containers / apps / division the function
-    /// needs to make in total.
+    ///   needs to make in total.
+    ///
+    /// * `branch` - Where this area is in the tree of splits. The ratio of its node is used
+    ///   instead of the split percentage if it has one
     ///
     /// # Returns
     ///
@@ -191,6 +1654,7 @@ impl BSPLayout {
         canvas_width: u32,
         canvas_height: u32,
         view_count: u32,
+        branch: Branch,
     ) -> Result<GeneratedLayout, BSPLayoutError> {
         let (half_view_count, views_remaining, mut layout) = self.setup_split(view_count)?;
 
@@ -207,49 +1671,29 @@ impl BSPLayout {
             return Ok(layout);
         }
 
-        let mut prime_split = (canvas_height as f32 * self.hsplit_perc) as u32;
-        if prime_split == 0 {
-            prime_split = 1;
-        }
-        if prime_split >= canvas_height {
-            prime_split = canvas_height - 1;
-        }
-        let sec_split = canvas_height - prime_split;
-
-        let (prime_sub, sec_sub) = if !self.reversed {
-            (self.ig_bottom, self.ig_top)
-        } else {
-            (self.ig_top, self.ig_bottom)
-        };
-
-        let (prime_y, sec_y) = if !self.reversed {
-            (origin_y, prime_split as i32 + origin_y + sec_sub as i32)
-        } else {
-            (sec_split as i32 + origin_y + prime_sub as i32, origin_y)
-        };
+        let ((prime_offset, prime_height), (sec_offset, sec_height)) =
+            self.split_parts(true, branch, canvas_height, view_count);
+        let prime_y = origin_y + prime_offset as i32;
+        let sec_y = origin_y + sec_offset as i32;
 
-        let mut prime_layout = self.vsplit(
+        let mut prime_layout = self.split(
             origin_x,
             prime_y,
             canvas_width,
-            if prime_sub < prime_split {
-                prime_split - prime_sub
-            } else {
-                1
-            },
+            prime_height,
             half_view_count,
+            false,
+            branch.child(Side::Primary, true),
         )?;
 
-        let mut sec_layout = self.vsplit(
+        let mut sec_layout = self.split(
             origin_x,
             sec_y,
             canvas_width,
-            if sec_sub < sec_split {
-                sec_split - sec_sub
-            } else {
-                1
-            },
+            sec_height,
             half_view_count + views_remaining,
+            false,
+            branch.child(Side::Secondary, true),
         )?;
 
         layout.views.append(&mut prime_layout.views);
@@ -258,31 +1702,35 @@ impl BSPLayout {
         Ok(layout)
     }
 
-    /// Divide the screen in two by splitting from top to bottom first, then subsequently from
-    /// right to left
+    /// Divide the area in two by splitting from top to bottom, then divide each half with
+    /// `split`. The direction of the splits inside each half comes from `split_direction`: they
+    /// split from right to left when the splits alternate, or across the longest side of the half
     ///
     /// # Arguments
     ///
     /// * `origin_x` - The x position of the top left of the space to be divided
-    /// relative to the entire display. For example, if you are dividing the entire
-    /// display, then the top left corner is 0, 0. If you are dividing the right
-    /// half of a 1920x1080 monitor, then the top left corner would be at 960, 0
+    ///   relative to the entire display. For example, if you are dividing the entire
+    ///   display, then the top left corner is 0, 0. If you are dividing the right
+    ///   half of a 1920x1080 monitor, then the top left corner would be at 960, 0
     ///
     /// * `origin_y` - The y position of the top left of the space to be divided
-    /// relative to the entire display. For example, if you are dividing the entire
-    /// display, then the top left corner is 0, 0. If you are dividing the bottom
-    /// half of a 1920x1080 monitor, then the top left corner would be at 0, 540
+    ///   relative to the entire display. For example, if you are dividing the entire
+    ///   display, then the top left corner is 0, 0. If you are dividing the bottom
+    ///   half of a 1920x1080 monitor, then the top left corner would be at 0, 540
     ///
     /// * `canvas_width` - The width in pixels of the area being divided. If you
-    /// are dividing all of a 1920x1080 monitor, then the `canvas_width` would be 1920.
-    /// If you are dividing the right half of the monitor, then the width is 960.
+    ///   are dividing all of a 1920x1080 monitor, then the `canvas_width` would be 1920.
+    ///   If you are dividing the right half of the monitor, then the width is 960.
     ///
     /// * `canvas_height` - The height in pixels of the area being divided. If you
-    /// are dividing all of a 1920x1080 monitor, then the height would be 1080.
-    /// If you are dividing the bottom half of the monitor, then the height is 540.
+    ///   are dividing all of a 1920x1080 monitor, then the height would be 1080.
+    ///   If you are dividing the bottom half of the monitor, then the height is 540.
     ///
     /// * `view_count` - How many windows / containers / apps / division the function
-    /// needs to make in total.
+    ///   needs to make in total.
+    ///
+    /// * `branch` - Where this area is in the tree of splits. The ratio of its node is used
+    ///   instead of the split percentage if it has one
     ///
     /// # Returns
     ///
@@ -295,6 +1743,7 @@ impl BSPLayout {
         canvas_width: u32,
         canvas_height: u32,
         view_count: u32,
+        branch: Branch,
     ) -> Result<GeneratedLayout, BSPLayoutError> {
         let (half_view_count, views_remaining, mut layout) = self.setup_split(view_count)?;
 
@@ -311,50 +1760,29 @@ impl BSPLayout {
             return Ok(layout);
         }
 
-        let mut prime_split = (canvas_width as f32 * self.vsplit_perc) as u32;
-        if prime_split == 0 {
-            prime_split = 1;
-        }
-        if prime_split >= canvas_width {
-            prime_split = canvas_width - 1;
-        }
-
-        let sec_split = canvas_width - prime_split;
-
-        let (prime_sub, sec_sub) = if !self.reversed {
-            (self.ig_right, self.ig_left)
-        } else {
-            (self.ig_left, self.ig_right)
-        };
-
-        let (prime_x, sec_x) = if !self.reversed {
-            (origin_x, prime_split as i32 + origin_x + sec_sub as i32)
-        } else {
-            (sec_split as i32 + origin_x + prime_sub as i32, origin_x)
-        };
+        let ((prime_offset, prime_width), (sec_offset, sec_width)) =
+            self.split_parts(false, branch, canvas_width, view_count);
+        let prime_x = origin_x + prime_offset as i32;
+        let sec_x = origin_x + sec_offset as i32;
 
-        let mut prime_layout = self.hsplit(
+        let mut prime_layout = self.split(
             prime_x,
             origin_y,
-            if prime_sub < prime_split {
-                prime_split - prime_sub
-            } else {
-                1
-            },
+            prime_width,
             canvas_height,
             half_view_count,
+            true,
+            branch.child(Side::Primary, false),
         )?;
 
-        let mut sec_layout = self.hsplit(
+        let mut sec_layout = self.split(
             sec_x,
             origin_y,
-            if sec_sub < sec_split {
-                sec_split - sec_sub
-            } else {
-                1
-            },
+            sec_width,
             canvas_height,
             half_view_count + views_remaining,
+            true,
+            branch.child(Side::Secondary, false),
         )?;
 
         layout.views.append(&mut prime_layout.views);
@@ -373,9 +1801,14 @@ impl Layout for BSPLayout {
     /// the gaps on each side of the screen as well as inner edges. Also supports setting all outer
     /// and inner gaps at the same time
     ///
+    /// When `tags` is given, only the state of those tags on `output` is changed. When the
+    /// compositor does not report the tags, the command is applied to the defaults of `output`
+    /// as well as every existing state of `output`, so the other outputs are left alone
+    ///
     /// # Examples
     ///
     /// ```
+    /// use river_bsp_layout::gap::Gap;
     /// use river_bsp_layout::BSPLayout;
     /// use river_layout_toolkit::Layout;
     ///
@@ -386,10 +1819,18 @@ impl Layout for BSPLayout {
     ///
     /// // Set gap between windows and the monitor edge to be 5 pixels
     /// let res = bsp.user_cmd("--outer-gap 5".to_string(), None, "eDP-1").unwrap();
-    /// assert_eq!(bsp.og_top, 5);
-    /// assert_eq!(bsp.og_bottom, 5);
-    /// assert_eq!(bsp.og_right, 5);
-    /// assert_eq!(bsp.og_left, 5);
+    /// let laptop = bsp.output_state("eDP-1").unwrap();
+    /// assert_eq!(laptop.og_top, Gap::Px(5));
+    /// assert_eq!(laptop.og_bottom, Gap::Px(5));
+    /// assert_eq!(laptop.og_right, Gap::Px(5));
+    /// assert_eq!(laptop.og_left, Gap::Px(5));
+    /// assert_eq!(bsp.og_top, Gap::Px(0));
+    ///
+    /// // Only change the split for the first tag of eDP-1
+    /// bsp.user_cmd("--vsplit-perc 0.6".to_string(), Some(1), "eDP-1").unwrap();
+    /// assert_eq!(bsp.state("eDP-1", 1).unwrap().vsplit_perc, 0.6);
+    /// assert!(bsp.state("DP-2", 1).is_none());
+    /// assert_eq!(bsp.vsplit_perc, 0.5);
     /// ```
     ///
     /// # Errors
@@ -399,12 +1840,12 @@ impl Layout for BSPLayout {
     fn user_cmd(
         &mut self,
         cmd: String,
-        _tags: Option<u32>,
-        _output: &str,
+        tags: Option<u32>,
+        output: &str,
     ) -> Result<(), Self::Error> {
-        let mut cmd: Vec<&str> = cmd.split(" ").collect();
-        cmd.insert(0, "");
-        let cmd = match user_cmd::UserCmd::try_parse_from(cmd) {
+        let mut cmd = user_cmd::split_args(&cmd);
+        cmd.insert(0, String::new());
+        let mut cmd = match user_cmd::UserCmd::try_parse_from(cmd) {
             Ok(c) => c,
             Err(e) => {
                 eprintln!("{}", e);
@@ -412,12 +1853,34 @@ impl Layout for BSPLayout {
             }
         };
 
-        cmd.handle_outer_gaps(self);
-        cmd.handle_inner_gaps(self);
-        cmd.handle_start_split(self)?;
-        cmd.handle_set_split(self);
-        cmd.handle_ch_split(self);
-        cmd.handle_reverse(self);
+        cmd.last_layout =
+            tags.and_then(|tags| self.last_layouts.get(&(output.to_string(), tags)).copied());
+        cmd.resolve_node()?;
+
+        if cmd.reload {
+            if let Err(e) = self.reload_config() {
+                eprintln!("Could not reload the config file: {}", e);
+                return Err(e);
+            }
+        }
+
+        match tags {
+            Some(tags) => self.state_mut(output, tags).apply_cmd(&cmd)?,
+            None => {
+                self.output_state_mut(output).apply_cmd(&cmd)?;
+                for ((o, _), state) in self.states.iter_mut() {
+                    if o == output {
+                        state.apply_cmd(&cmd)?;
+                    }
+                }
+            }
+        }
+
+        if let Some(state_file) = &self.state_file {
+            if let Err(e) = state_file.save(self) {
+                eprintln!("Could not save the layout state: {}", e);
+            }
+        }
 
         Ok(())
     }
@@ -429,9 +1892,10 @@ impl Layout for BSPLayout {
     /// * `view_count` - The number of views / windows / containers to divide the screen into
     /// * `usable_width` - How many pixels wide the whole display is
     /// * `usable_height` - How many pixels tall the whole display is
-    /// * `_tags` - Int representing which tags are currently active based on which
-    /// bit is toggled
-    /// * `_output` - The name of the output to generate the layout on
+    /// * `tags` - Int representing which tags are currently active based on which
+    ///   bit is toggled
+    /// * `output` - The name of the output to generate the layout on. Together with `tags`, used
+    ///   to look up the state that `user_cmd` kept for this output
     ///
     /// # Examples
     ///
@@ -447,25 +1911,22 @@ impl Layout for BSPLayout {
         view_count: u32,
         usable_width: u32,
         usable_height: u32,
-        _tags: u32,
-        _output: &str,
+        tags: u32,
+        output: &str,
     ) -> Result<GeneratedLayout, Self::Error> {
-        if !self.start_hsplit {
-            Ok(self.vsplit(
-                self.og_left as i32,
-                self.og_top as i32,
-                usable_width - self.og_left - self.og_right,
-                usable_height - self.og_top - self.og_bottom,
-                view_count,
-            ))?
+        self.last_layouts.insert(
+            (output.to_string(), tags),
+            (view_count, usable_width, usable_height),
+        );
+
+        let state = if self.states.contains_key(&(output.to_string(), tags))
+            || self.output_states.contains_key(output)
+            || self.has_options_for(output, tags)
+        {
+            self.state_mut(output, tags)
         } else {
-            Ok(self.hsplit(
-                self.og_left as i32,
-                self.og_top as i32,
-                usable_width - self.og_left - self.og_right,
-                usable_height - self.og_top - self.og_bottom,
-                view_count,
-            ))?
-        }
+            self
+        };
+        state.layout_area(view_count, usable_width, usable_height)
     }
 }
@@ -1,6 +1,14 @@
+use std::path::PathBuf;
+use std::process::Command;
+
 use clap::Parser;
-use river_bsp_layout::BSPLayout;
-use river_layout_toolkit::run;
+use river_bsp_layout::config::{Config, ConfigSource, LayoutOptions};
+use river_bsp_layout::gap::{Gap, GapScale};
+use river_bsp_layout::grid::GridFill;
+use river_bsp_layout::master_stack::MainLocation;
+use river_bsp_layout::state::StateFile;
+use river_bsp_layout::{BSPLayout, LayoutMode, SmartGaps, SplitDirection, StackSizing};
+use river_layout_toolkit::{run, Layout};
 
 /// Layout manager for Wayland tiling compositor River. Creates a grid like Binary Space
 /// Partitioned layout where each window is made as equal in size as possible while still
@@ -8,120 +16,381 @@ use river_layout_toolkit::run;
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
-    /// The number of pixels to pad each inner edge of a window by default.
-    #[arg(
-        short = 'i',
-        long = "inner-gap",
-        default_value_t = 0,
-        help_heading = "Inner Gap Options"
-    )]
-    default_inner_gap: u32,
+    /// The number of pixels to pad each inner edge of a window by default. Every gap option takes
+    /// pixels such as `10` or `10px`, or a percentage of the output such as `1%`. Defaults to 0.
+    #[arg(short = 'i', long = "inner-gap", help_heading = "Inner Gap Options")]
+    default_inner_gap: Option<Gap>,
 
     /// The number of pixels to pad the left inner edge of each window. This Overrides
     /// `default_inner_gap`. Optional
     #[arg(long, short = 'l', help_heading = "Inner Gap Options")]
-    ig_left: Option<u32>,
+    ig_left: Option<Gap>,
 
     /// The number of pixels to pad the right inner edge of each window. This Overrides
     /// `default_inner_gap`. Optional
     #[arg(long, short = 'r', help_heading = "Inner Gap Options")]
-    ig_right: Option<u32>,
+    ig_right: Option<Gap>,
 
     /// The number of pixels to pad the bottom inner edge of each window. This Overrides
     /// `default_inner_gap`. Optional
     #[arg(long, short = 'b', help_heading = "Inner Gap Options")]
-    ig_bottom: Option<u32>,
+    ig_bottom: Option<Gap>,
 
     /// The number of pixels to pad the top inner edge of each window. This Overrides
     /// `default_inner_gap`. Optional
     #[arg(long, short = 't', help_heading = "Inner Gap Options")]
-    ig_top: Option<u32>,
+    ig_top: Option<Gap>,
 
-    /// The default size of the gap between windows and the edge of the screen.
-    #[arg(
-        short = 'o',
-        long = "outer-gap",
-        default_value_t = 0,
-        help_heading = "Outer Gap Options"
-    )]
-    default_outer_gap: u32,
+    /// The default size of the gap between windows and the edge of the screen, in pixels such as
+    /// `10` or `10px`, or as a percentage of the output such as `1%`. Defaults to 0.
+    #[arg(short = 'o', long = "outer-gap", help_heading = "Outer Gap Options")]
+    default_outer_gap: Option<Gap>,
 
     /// The number of pixels to place between the left screen edge and any windows. Overrides
     /// `default_outer_gap` for the left side. Optional.
     #[arg(long, short = 'L', help_heading = "Outer Gap Options")]
-    og_left: Option<u32>,
+    og_left: Option<Gap>,
 
     /// The number of pixels to place between the right screen edge and any windows. Overrides
     /// `default_outer_gap` for the right side. Optional.
     #[arg(long, short = 'R', help_heading = "Outer Gap Options")]
-    og_right: Option<u32>,
+    og_right: Option<Gap>,
 
     /// The number of pixels to place between the bottom screen edge and any windows. Overrides
     /// `default_outer_gap` for the bottom side. Optional.
     #[arg(long, short = 'B', help_heading = "Outer Gap Options")]
-    og_bottom: Option<u32>,
+    og_bottom: Option<Gap>,
 
     /// The number of pixels to place between the top screen edge and any windows. Overrides
     /// `default_outer_gap` for the top side. Optional.
     #[arg(long, short = 'T', help_heading = "Outer Gap Options")]
-    og_top: Option<u32>,
+    og_top: Option<Gap>,
+
+    /// Leave out the outer gaps while there is only one view (`single`), and also in monocle and
+    /// the inner gaps of the layouts that stack the views (`stacked`). Passing the option without a value picks
+    /// `single`. Defaults to `off`.
+    #[arg(
+        long,
+        value_enum,
+        num_args = 0..=1,
+        default_missing_value = "single",
+        help_heading = "Outer Gap Options"
+    )]
+    smart_gaps: Option<SmartGaps>,
+
+    /// Scale the inner and outer gaps by the number of views, such as `1.0,0.75,0.5@6`. Each
+    /// scale is for one more view than the one before it, starting at 1 view, unless `@` gives its
+    /// view count, and the gaps shrink evenly between two scales. Defaults to no scaling.
+    #[arg(long, help_heading = "Outer Gap Options")]
+    gap_scale: Option<GapScale>,
 
     /// The default percentage of available area that the primary window should occupy after any
-    /// split takes place.
+    /// split takes place. A comma separated list such as `0.6,0.5` sets the percentage of each
+    /// level of splits, and the last one is used for every level below. Defaults to 0.5.
     #[arg(
         long = "split-perc",
         short = 's',
-        default_value_t = 0.5,
+        value_delimiter = ',',
         help_heading = "Split Options"
     )]
-    default_split_perc: f32,
+    default_split_perc: Option<Vec<f32>>,
 
     /// The percentage of available area that the primary window should occupy after a horizontal
     /// split. This will override the value of `default_split_perc` only for horizontal splits.
-    #[arg(long, short = 'H', help_heading = "Split Options")]
-    hsplit_perc: Option<f32>,
+    /// Takes a list like `--split-perc`.
+    #[arg(
+        long,
+        short = 'H',
+        value_delimiter = ',',
+        help_heading = "Split Options"
+    )]
+    hsplit_perc: Option<Vec<f32>>,
 
     /// The percentage of available area that the primary window should occupy after a vertical
     /// split. This will override the value of `default_split_perc` only for vertical splits.
-    #[arg(long, short, help_heading = "Split Options")]
-    vsplit_perc: Option<f32>,
+    /// Takes a list like `--split-perc`.
+    #[arg(long, short, value_delimiter = ',', help_heading = "Split Options")]
+    vsplit_perc: Option<Vec<f32>>,
+
+    /// How much of the difference between the split percentage and 0.5 is kept at every level of
+    /// splits, between 0.0 and 1.0. Lower values pull deeper splits towards an even split, so the
+    /// first view stays the largest without making the deepest views too small. Defaults to 1.0.
+    #[arg(long, help_heading = "Split Options")]
+    split_decay: Option<f32>,
+
+    /// The number of pixels that the primary side of the first horizontal split of the `bsp`
+    /// layout occupies, instead of the hsplit percentage. It is clamped to the height of the
+    /// split. Defaults to 0, which uses the percentage.
+    #[arg(long, help_heading = "Split Options")]
+    hsplit_px: Option<u32>,
+
+    /// The number of pixels that the primary side of the first vertical split of the `bsp` layout
+    /// occupies, instead of the vsplit percentage. It is clamped to the width of the split.
+    /// Defaults to 0, which uses the percentage.
+    #[arg(long, help_heading = "Split Options")]
+    vsplit_px: Option<u32>,
 
     /// Whether the first split that's made should divide the screen horizontally. If this is not
     /// set, then the first split will be vertical.
-    #[arg(long, help_heading = "Split Options")]
+    #[arg(long, overrides_with = "start_vsplit", help_heading = "Split Options")]
     start_hsplit: bool,
 
+    /// Make the first split divide the screen vertically, even if the config file sets
+    /// `start-hsplit`.
+    #[arg(long, overrides_with = "start_hsplit", help_heading = "Split Options")]
+    start_vsplit: bool,
+
     /// Reverse the order of the views as well as the order they are added.
-    #[arg(long, help_heading = "Other Options")]
+    #[arg(long, overrides_with = "no_reverse", help_heading = "Other Options")]
     reverse: bool,
+
+    /// Keep the order of the views, even if the config file sets `reverse`.
+    #[arg(long, overrides_with = "reverse", help_heading = "Other Options")]
+    no_reverse: bool,
+
+    /// Format of the layout name that is reported to river and status bars. `{mode}`, `{count}`,
+    /// `{hsplit}`, `{vsplit}`, `{reversed}`, `{start}` and `{monocle}` are replaced by the active
+    /// layout, the number of views, the split percentages, `⇄` when reversed, `h` or `v` for the
+    /// first split, and the number of views in monocle mode. River does not tell layouts which
+    /// view is focused, so `{monocle}` cannot show the index of the visible view. Defaults to
+    /// `{mode}`.
+    #[arg(long, help_heading = "Other Options")]
+    name_format: Option<String>,
+
+    /// The algorithm used to place the views. Defaults to `bsp`.
+    #[arg(long, value_enum, help_heading = "Layout Options")]
+    layout: Option<LayoutMode>,
+
+    /// How many views are placed in the main area of the `master-stack` and `centered-master`
+    /// layouts. Defaults to 1.
+    #[arg(long, help_heading = "Layout Options")]
+    main_count: Option<u32>,
+
+    /// The percentage of space that the main area of the `master-stack` layout occupies, or the
+    /// width of the center column of the `centered-master` layout. Defaults to 0.6.
+    #[arg(long, help_heading = "Layout Options")]
+    main_ratio: Option<f32>,
+
+    /// Which side of the screen the main area of the `master-stack` layout is placed on.
+    /// Defaults to `left`.
+    #[arg(long, value_enum, help_heading = "Layout Options")]
+    main_location: Option<MainLocation>,
+
+    /// How many columns the `grid` layout has. 0 fits the columns to the number of views.
+    /// Defaults to 0.
+    #[arg(long, help_heading = "Layout Options")]
+    grid_cols: Option<u32>,
+
+    /// How many rows the `grid` layout has. 0 fits the rows to the number of views. Defaults to
+    /// 0.
+    #[arg(long, help_heading = "Layout Options")]
+    grid_rows: Option<u32>,
+
+    /// What to do with the last row of the `grid` layout when there are not enough views to fill
+    /// it. Defaults to `stretch`.
+    #[arg(long, value_enum, help_heading = "Layout Options")]
+    grid_fill: Option<GridFill>,
+
+    /// How the space is shared between the views of the `columns` and `rows` layouts. With
+    /// `geometric`, each view gets the vsplit (columns) or hsplit (rows) percentage of the space
+    /// left by the views before it. Defaults to `equal`.
+    #[arg(long, value_enum, help_heading = "Layout Options")]
+    stack_sizing: Option<StackSizing>,
+
+    /// Comma separated list of the layouts that the `--next-layout` and `--prev-layout` user
+    /// commands cycle through. Defaults to every layout.
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        help_heading = "Layout Options"
+    )]
+    layout_cycle: Option<Vec<LayoutMode>>,
+
+    /// Weight each split by the number of views that end up on either side, so every view gets
+    /// roughly the same area. The split percentages then bias the split towards the primary side
+    /// when above 0.5, or away from it when below 0.5.
+    #[arg(long, overrides_with = "no_equal_area", help_heading = "Split Options")]
+    equal_area: bool,
+
+    /// Use the split percentages as they are, even if the config file sets `equal-area`.
+    #[arg(long, overrides_with = "equal_area", help_heading = "Split Options")]
+    no_equal_area: bool,
+
+    /// How the direction of each split is chosen. `alternate` switches between vertical and
+    /// horizontal splits, while `longest-side` splits each container across its longest side.
+    /// Defaults to `alternate`.
+    #[arg(long, value_enum, help_heading = "Split Options")]
+    split_direction: Option<SplitDirection>,
+
+    /// Only used with `--split-direction longest-side`. A container is split vertically when its
+    /// width is more than this many times its height. Defaults to 1.0.
+    #[arg(long, help_heading = "Split Options")]
+    split_bias: Option<f32>,
+
+    /// Path to a TOML config file. Options passed on the command line take precedence over the
+    /// file. Defaults to `$XDG_CONFIG_HOME/river-bsp-layout/config.toml` if that file exists.
+    #[arg(long, short = 'c', help_heading = "Config Options")]
+    config: Option<PathBuf>,
+
+    /// Watch the config file, and reload it whenever it is saved. The `--reload` user command is
+    /// sent through `riverctl`, so river lays out the focused output again right away.
+    #[arg(long, help_heading = "Config Options")]
+    watch_config: bool,
+
+    /// Save the changes that user commands make to each output and tag, and restore them the next
+    /// time the layout is started. The state is kept in
+    /// `$XDG_STATE_HOME/river-bsp-layout/state.toml` unless `--state-file` is passed.
+    #[arg(long, help_heading = "State Options")]
+    persist_state: bool,
+
+    /// Path of the file to save the state to. Implies `--persist-state`.
+    #[arg(long, help_heading = "State Options")]
+    state_file: Option<PathBuf>,
+}
+
+impl Cli {
+    /// Collect the layout options that were passed on the command line, so they can be applied
+    /// on top of the config file
+    fn options(&self) -> LayoutOptions {
+        let (split_perc, deeper_split_perc) = depth_percs(&self.default_split_perc);
+        let (hsplit_perc, deeper_hsplit_perc) = depth_percs(&self.hsplit_perc);
+        let (vsplit_perc, deeper_vsplit_perc) = depth_percs(&self.vsplit_perc);
+
+        LayoutOptions {
+            inner_gap: self.default_inner_gap,
+            ig_left: self.ig_left,
+            ig_right: self.ig_right,
+            ig_bottom: self.ig_bottom,
+            ig_top: self.ig_top,
+            outer_gap: self.default_outer_gap,
+            og_left: self.og_left,
+            og_right: self.og_right,
+            og_bottom: self.og_bottom,
+            og_top: self.og_top,
+            smart_gaps: self.smart_gaps,
+            gap_scale: self.gap_scale.clone(),
+            split_perc,
+            hsplit_perc,
+            vsplit_perc,
+            deeper_split_perc,
+            deeper_hsplit_perc,
+            deeper_vsplit_perc,
+            split_decay: self.split_decay,
+            hsplit_px: self.hsplit_px,
+            vsplit_px: self.vsplit_px,
+            start_hsplit: flag(self.start_hsplit, self.start_vsplit),
+            reverse: flag(self.reverse, self.no_reverse),
+            equal_area: flag(self.equal_area, self.no_equal_area),
+            split_direction: self.split_direction,
+            split_bias: self.split_bias,
+            layout: self.layout,
+            main_count: self.main_count,
+            main_ratio: self.main_ratio,
+            main_location: self.main_location,
+            grid_cols: self.grid_cols,
+            grid_rows: self.grid_rows,
+            grid_fill: self.grid_fill,
+            stack_sizing: self.stack_sizing,
+            layout_cycle: self.layout_cycle.clone(),
+            name_format: self.name_format.clone(),
+            split_tree: None,
+        }
+    }
+
+    /// Get the config file passed with `--config`, or the one at the default location
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - Options that the config file is applied on top of
+    ///
+    /// # Returns
+    ///
+    /// `None` if no path was passed and there is no default location. A missing file at the
+    /// default location is treated as an empty config
+    fn config_source(&self, base: LayoutOptions) -> Option<ConfigSource> {
+        let (path, required) = match &self.config {
+            Some(path) => (path.clone(), true),
+            None => (Config::default_path()?, false),
+        };
+
+        Some(ConfigSource::new(path, required, base, self.options()))
+    }
+
+    /// Get the file to save the state to, if the state should be saved at all
+    fn state_file(&self) -> Option<StateFile> {
+        match &self.state_file {
+            Some(path) => Some(StateFile::new(path.clone())),
+            None if self.persist_state => StateFile::default_path().map(StateFile::new),
+            None => None,
+        }
+    }
+}
+
+/// Separate a list of split percentages passed on the command line into the percentage of the
+/// first level and the percentages of the levels below it
+fn depth_percs(percs: &Option<Vec<f32>>) -> (Option<f32>, Option<Vec<f32>>) {
+    match percs.as_deref() {
+        Some([perc, deeper @ ..]) => (Some(*perc), Some(deeper.to_vec())),
+        _ => (None, None),
+    }
+}
+
+/// Turn a flag and the flag that negates it into an option, which is `None` if neither was passed
+fn flag(set: bool, unset: bool) -> Option<bool> {
+    match (set, unset) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+/// Send the `--reload` user command to the layout through `riverctl`. River asks for a new layout
+/// of the focused output after every user command, so the change shows up right away
+fn send_reload() {
+    let status = Command::new("riverctl")
+        .args(["send-layout-cmd", BSPLayout::NAMESPACE, "--reload"])
+        .status();
+    if let Err(e) = status {
+        eprintln!("Could not send the reload command: {}", e);
+    }
 }
 
 fn main() {
     let cli = Cli::parse();
-    let mut layout = BSPLayout::new();
-    layout.ig_left = cli.ig_left.unwrap_or(cli.default_inner_gap);
-    layout.ig_right = cli.ig_right.unwrap_or(cli.default_inner_gap);
-    layout.ig_bottom = cli.ig_bottom.unwrap_or(cli.default_inner_gap);
-    layout.ig_top = cli.ig_top.unwrap_or(cli.default_inner_gap);
-
-    layout.og_left = cli.og_left.unwrap_or(cli.default_outer_gap);
-    layout.og_right = cli.og_right.unwrap_or(cli.default_outer_gap);
-    layout.og_bottom = cli.og_bottom.unwrap_or(cli.default_outer_gap);
-    layout.og_top = cli.og_top.unwrap_or(cli.default_outer_gap);
-
-    layout.hsplit_perc = cli.hsplit_perc.unwrap_or(cli.default_split_perc);
-    layout.vsplit_perc = cli.vsplit_perc.unwrap_or(cli.default_split_perc);
-    if layout.hsplit_perc <= 0.0
-        || layout.hsplit_perc >= 1.0
-        || layout.vsplit_perc <= 0.0
-        || layout.vsplit_perc >= 1.0
-    {
-        println!("Split percentages must be greater than 0 and less than 1");
-        return;
+    let mut defaults = BSPLayout::new();
+    defaults.set_all_inner_gaps(0);
+    defaults.set_all_outer_gaps(0);
+
+    let source = cli.config_source(LayoutOptions::from(&defaults));
+    let layout = match &source {
+        Some(source) => source.build(),
+        None => {
+            cli.options().apply(&mut defaults);
+            LayoutOptions::from(&defaults).validate().map(|_| defaults)
+        }
+    };
+
+    let mut layout = match layout {
+        Ok(l) => l,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+
+    if let Some(state_file) = cli.state_file() {
+        if let Err(e) = state_file.restore(&mut layout) {
+            eprintln!("Could not restore the layout state: {}", e);
+        }
+        layout.persist_to(state_file);
     }
 
-    layout.reversed = cli.reverse;
-    layout.start_hsplit = cli.start_hsplit;
+    if cli.watch_config {
+        if let Some(Err(e)) = source.map(|s| s.watch(send_reload)) {
+            eprintln!("{}", e);
+        }
+    }
 
     run(layout).unwrap();
 }
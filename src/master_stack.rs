@@ -0,0 +1,253 @@
+use clap::ValueEnum;
+use river_layout_toolkit::GeneratedLayout;
+use serde::{Deserialize, Serialize};
+
+use crate::tree::Branch;
+use crate::{BSPLayout, BSPLayoutError};
+
+/// Which side of the screen the main area of `LayoutMode::MasterStack` is placed on
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MainLocation {
+    /// Main views on the left, stack on the right
+    #[default]
+    Left,
+
+    /// Main views on the right, stack on the left
+    Right,
+
+    /// Main views on the top, stack on the bottom
+    Top,
+
+    /// Main views on the bottom, stack on the top
+    Bottom,
+}
+
+impl BSPLayout {
+    /// Divide the area into a main area holding the first `main_count` views and a stack holding
+    /// the rest. The main area occupies `main_ratio` of the area on the side given by
+    /// `main_location`. If there are no views for one of the two areas, the other area takes up
+    /// all of the space
+    ///
+    /// # Arguments
+    ///
+    /// * `origin_x` - The x position of the top left of the space to be divided
+    /// * `origin_y` - The y position of the top left of the space to be divided
+    /// * `canvas_width` - The width in pixels of the area being divided
+    /// * `canvas_height` - The height in pixels of the area being divided
+    /// * `view_count` - How many views the function needs to make in total
+    ///
+    /// # Returns
+    ///
+    /// A `GeneratedLayout` with `view_count` cells
+    ///
+    /// # Errors
+    ///
+    /// Will return `BSPLayoutError::LayoutError` if `main_ratio` is not > 0.0 and < 1.0
+    pub(crate) fn master_stack(
+        &self,
+        origin_x: i32,
+        origin_y: i32,
+        canvas_width: u32,
+        canvas_height: u32,
+        view_count: u32,
+    ) -> Result<GeneratedLayout, BSPLayoutError> {
+        if self.main_ratio <= 0.0 || self.main_ratio >= 1.0 {
+            return Err(BSPLayoutError::LayoutError(
+                "Main ratio must be > 0.0 and less than 1.0".to_string(),
+            ));
+        }
+
+        let mut layout = GeneratedLayout {
+            layout_name: String::new(),
+            views: Vec::with_capacity(view_count as usize),
+        };
+
+        let main_count = self.main_count.min(view_count);
+        let stack_count = view_count - main_count;
+        // Main views are stacked across the short side of the main area
+        let rows = matches!(self.main_location, MainLocation::Left | MainLocation::Right);
+
+        if main_count == 0 || stack_count == 0 {
+            layout.views = self.stack(
+                origin_x,
+                origin_y,
+                canvas_width,
+                canvas_height,
+                &vec![1.0; view_count as usize],
+                rows,
+            );
+            return Ok(layout);
+        }
+
+        let (main, stack) = if rows {
+            let (left, right) = self.divide(
+                origin_x,
+                canvas_width,
+                self.main_ratio,
+                self.main_location == MainLocation::Right,
+                self.gaps.ig_right,
+                self.gaps.ig_left,
+            );
+            (
+                (left.0, origin_y, left.1, canvas_height),
+                (right.0, origin_y, right.1, canvas_height),
+            )
+        } else {
+            let (top, bottom) = self.divide(
+                origin_y,
+                canvas_height,
+                self.main_ratio,
+                self.main_location == MainLocation::Bottom,
+                self.gaps.ig_bottom,
+                self.gaps.ig_top,
+            );
+            (
+                (origin_x, top.0, canvas_width, top.1),
+                (origin_x, bottom.0, canvas_width, bottom.1),
+            )
+        };
+
+        layout.views = self.stack(
+            main.0,
+            main.1,
+            main.2,
+            main.3,
+            &vec![1.0; main_count as usize],
+            rows,
+        );
+        layout.views.append(&mut self.stack(
+            stack.0,
+            stack.1,
+            stack.2,
+            stack.3,
+            &vec![1.0; stack_count as usize],
+            rows,
+        ));
+
+        Ok(layout)
+    }
+
+    /// Place the first `main_count` views in a column in the middle of the area that is
+    /// `main_ratio` of its width, and alternate the rest between a stack on the right and a stack
+    /// on the left, starting with the right. Each side stack is partitioned like the BSP layout.
+    /// While there is only a right stack, the main column sits on the left instead of the middle
+    ///
+    /// # Arguments
+    ///
+    /// * `origin_x` - The x position of the top left of the space to be divided
+    /// * `origin_y` - The y position of the top left of the space to be divided
+    /// * `canvas_width` - The width in pixels of the area being divided
+    /// * `canvas_height` - The height in pixels of the area being divided
+    /// * `view_count` - How many views the function needs to make in total
+    ///
+    /// # Returns
+    ///
+    /// A `GeneratedLayout` with `view_count` cells
+    ///
+    /// # Errors
+    ///
+    /// Will return `BSPLayoutError::LayoutError` if `main_ratio` or either split percentage is not
+    /// > 0.0 and < 1.0
+    pub(crate) fn centered_master(
+        &self,
+        origin_x: i32,
+        origin_y: i32,
+        canvas_width: u32,
+        canvas_height: u32,
+        view_count: u32,
+    ) -> Result<GeneratedLayout, BSPLayoutError> {
+        if self.main_ratio <= 0.0 || self.main_ratio >= 1.0 {
+            return Err(BSPLayoutError::LayoutError(
+                "Main ratio must be > 0.0 and less than 1.0".to_string(),
+            ));
+        }
+
+        let main_count = self.main_count.min(view_count);
+        let stack_count = view_count - main_count;
+        if main_count == 0 || stack_count == 0 {
+            return self.master_stack(origin_x, origin_y, canvas_width, canvas_height, view_count);
+        }
+
+        let right_count = stack_count.div_ceil(2);
+        let left_count = stack_count / 2;
+        let side_ratio = (1.0 - self.main_ratio) / 2.0;
+        let columns = if left_count == 0 {
+            self.stack(
+                origin_x,
+                origin_y,
+                canvas_width,
+                canvas_height,
+                &[self.main_ratio, 1.0 - self.main_ratio],
+                false,
+            )
+        } else {
+            let mut columns = self.stack(
+                origin_x,
+                origin_y,
+                canvas_width,
+                canvas_height,
+                &[side_ratio, self.main_ratio, side_ratio],
+                false,
+            );
+            // Keep the order of the columns as main, right, left
+            columns.rotate_left(1);
+            columns
+        };
+
+        let mut layout = GeneratedLayout {
+            layout_name: String::new(),
+            views: Vec::with_capacity(view_count as usize),
+        };
+        let main = &columns[0];
+        layout.views = self.stack(
+            main.x,
+            main.y,
+            main.width,
+            main.height,
+            &vec![1.0; main_count as usize],
+            true,
+        );
+
+        let right = &columns[1];
+        let mut right_views = self
+            .split(
+                right.x,
+                right.y,
+                right.width,
+                right.height,
+                right_count,
+                self.start_hsplit,
+                Branch::default(),
+            )?
+            .views
+            .into_iter();
+        let mut left_views = match columns.get(2) {
+            Some(left) => {
+                self.split(
+                    left.x,
+                    left.y,
+                    left.width,
+                    left.height,
+                    left_count,
+                    self.start_hsplit,
+                    Branch::default(),
+                )?
+                .views
+            }
+            None => Vec::new(),
+        }
+        .into_iter();
+
+        for i in 0..stack_count {
+            let view = if i % 2 == 0 {
+                right_views.next()
+            } else {
+                left_views.next()
+            };
+            layout.views.extend(view);
+        }
+
+        Ok(layout)
+    }
+}
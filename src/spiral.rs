@@ -0,0 +1,93 @@
+use river_layout_toolkit::{GeneratedLayout, Rectangle};
+
+use crate::{BSPLayout, BSPLayoutError, SplitDirection};
+
+impl BSPLayout {
+    /// Give each view the primary side of a split of the cell that was created last, so every
+    /// new view takes space from the view before it. With `rotate` unset this dwindles towards
+    /// the bottom right corner (or the top left if `reversed` is set). With `rotate` set, the
+    /// primary side moves around the corners of the screen, which gives a spiral
+    ///
+    /// # Arguments
+    ///
+    /// * `origin_x` - The x position of the top left of the space to be divided
+    /// * `origin_y` - The y position of the top left of the space to be divided
+    /// * `canvas_width` - The width in pixels of the area being divided
+    /// * `canvas_height` - The height in pixels of the area being divided
+    /// * `view_count` - How many views the function needs to make in total
+    /// * `rotate` - Whether to spiral instead of dwindle
+    ///
+    /// # Returns
+    ///
+    /// A `GeneratedLayout` with `view_count` cells
+    ///
+    /// # Errors
+    ///
+    /// Will return `BSPLayoutError::LayoutError` if either split percentage is not > 0.0 and
+    /// < 1.0
+    pub(crate) fn spiral(
+        &self,
+        origin_x: i32,
+        origin_y: i32,
+        canvas_width: u32,
+        canvas_height: u32,
+        view_count: u32,
+        rotate: bool,
+    ) -> Result<GeneratedLayout, BSPLayoutError> {
+        let (_, _, mut layout) = self.setup_split(view_count)?;
+
+        let (mut x, mut y, mut width, mut height) =
+            (origin_x, origin_y, canvas_width, canvas_height);
+        let mut hsplit = self.start_hsplit;
+        // Every view is inside all of the splits before it
+        let (mut hsplit_depth, mut vsplit_depth) = (0, 0);
+        for i in 0..view_count {
+            // The last view takes up whatever is left
+            if i == view_count - 1 {
+                layout.views.push(Rectangle {
+                    x,
+                    y,
+                    width,
+                    height,
+                });
+                break;
+            }
+
+            let hsplit_now = match self.split_direction {
+                SplitDirection::Alternate => hsplit,
+                SplitDirection::LongestSide => width as f32 <= height as f32 * self.split_bias,
+            };
+            // Every second pair of splits puts the new view on the far side when spiraling
+            let swap = self.reversed != (rotate && i % 4 >= 2);
+
+            if hsplit_now {
+                let perc = self.split_perc(self.depth_perc(true, hsplit_depth), 1, view_count - i);
+                hsplit_depth += 1;
+                let (prime, rest) =
+                    self.divide(y, height, perc, swap, self.gaps.ig_bottom, self.gaps.ig_top);
+                layout.views.push(Rectangle {
+                    x,
+                    y: prime.0,
+                    width,
+                    height: prime.1,
+                });
+                (y, height) = rest;
+            } else {
+                let perc = self.split_perc(self.depth_perc(false, vsplit_depth), 1, view_count - i);
+                vsplit_depth += 1;
+                let (prime, rest) =
+                    self.divide(x, width, perc, swap, self.gaps.ig_right, self.gaps.ig_left);
+                layout.views.push(Rectangle {
+                    x: prime.0,
+                    y,
+                    width: prime.1,
+                    height,
+                });
+                (x, width) = rest;
+            }
+            hsplit = !hsplit_now;
+        }
+
+        Ok(layout)
+    }
+}
@@ -0,0 +1,259 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::LayoutOptions;
+use crate::{BSPLayout, BSPLayoutError, LayoutMode, SmartGaps};
+
+/// What the toggle commands of a layout go back to. These are not options, so they are saved
+/// next to the options rather than with them
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct SavedToggles {
+    /// The layout that `--toggle-monocle` goes back to
+    pub previous_layout: Option<LayoutMode>,
+
+    /// The smart gaps that `--toggle-smart-gaps` goes back to
+    pub previous_smart_gaps: Option<SmartGaps>,
+
+    /// Whether `--toggle-gaps` turned the gaps off
+    pub gaps_enabled: Option<bool>,
+}
+
+impl From<&BSPLayout> for SavedToggles {
+    fn from(layout: &BSPLayout) -> Self {
+        SavedToggles {
+            previous_layout: Some(layout.previous_mode),
+            previous_smart_gaps: Some(layout.previous_smart_gaps),
+            gaps_enabled: Some(layout.gaps_enabled),
+        }
+    }
+}
+
+impl SavedToggles {
+    /// Make the toggle commands of `layout` go back to the saved values
+    ///
+    /// # Arguments
+    ///
+    /// * `layout` - The layout to change
+    pub fn apply(&self, layout: &mut BSPLayout) {
+        if let Some(m) = self.previous_layout {
+            layout.previous_mode = m;
+        }
+        if let Some(g) = self.previous_smart_gaps {
+            layout.previous_smart_gaps = g;
+        }
+        if let Some(e) = self.gaps_enabled {
+            layout.gaps_enabled = e;
+        }
+    }
+}
+
+/// The state of a single output and tag bitmask as it is written to the state file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SavedState {
+    /// The name of the output, such as `eDP-1`
+    pub output: String,
+
+    /// Int representing which tags are active based on which bit is toggled
+    pub tags: u32,
+
+    /// The options that user commands changed since the state was created
+    #[serde(flatten)]
+    pub options: LayoutOptions,
+
+    /// What the toggle commands of the state go back to
+    #[serde(flatten)]
+    pub toggles: SavedToggles,
+}
+
+/// The defaults of a single output as they are written to the state file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SavedOutput {
+    /// The name of the output, such as `eDP-1`
+    pub output: String,
+
+    /// The options that commands without tags changed since the defaults of the output were
+    /// created
+    #[serde(flatten)]
+    pub options: LayoutOptions,
+
+    /// What the toggle commands of the defaults of the output go back to
+    #[serde(flatten)]
+    pub toggles: SavedToggles,
+}
+
+/// Contents of the state file. Only the changes made by user commands are saved, so options
+/// that were not changed at runtime keep following the command line and the config file
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct SavedLayout {
+    /// The defaults of every output that has its own
+    #[serde(default)]
+    pub outputs: Vec<SavedOutput>,
+
+    /// The state of every output and tag bitmask that has one
+    #[serde(default)]
+    pub states: Vec<SavedState>,
+}
+
+impl From<&BSPLayout> for SavedLayout {
+    fn from(layout: &BSPLayout) -> Self {
+        let mut states: Vec<SavedState> = layout
+            .states
+            .iter()
+            .map(|((output, tags), state)| SavedState {
+                output: output.clone(),
+                tags: *tags,
+                options: LayoutOptions::from(state).changed_from(&state.initial),
+                toggles: SavedToggles::from(state),
+            })
+            .collect();
+        // Keep the file stable between writes, since the maps have no order
+        states.sort_by(|a, b| (&a.output, a.tags).cmp(&(&b.output, b.tags)));
+
+        let mut outputs: Vec<SavedOutput> = layout
+            .output_states
+            .iter()
+            .map(|(output, state)| SavedOutput {
+                output: output.clone(),
+                options: LayoutOptions::from(state).changed_from(&state.initial),
+                toggles: SavedToggles::from(state),
+            })
+            .collect();
+        outputs.sort_by(|a, b| a.output.cmp(&b.output));
+
+        SavedLayout { outputs, states }
+    }
+}
+
+impl SavedLayout {
+    /// Check that every saved split percentage is in range
+    ///
+    /// # Errors
+    ///
+    /// Will return `BSPLayoutError::StateError` naming the first option that is out of range
+    pub fn validate(&self) -> Result<(), BSPLayoutError> {
+        for options in self
+            .outputs
+            .iter()
+            .map(|o| &o.options)
+            .chain(self.states.iter().map(|s| &s.options))
+        {
+            options
+                .validate()
+                .map_err(|e| BSPLayoutError::StateError(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Replace the output defaults and states of `layout` with new ones that have the saved
+    /// changes applied on top of the defaults and config sections of `layout`
+    ///
+    /// # Arguments
+    ///
+    /// * `layout` - The layout to restore the state of
+    pub fn apply(&self, layout: &mut BSPLayout) {
+        layout.states.clear();
+        layout.output_states.clear();
+        // The outputs go first, since the states of an output are created from its defaults
+        for output in &self.outputs {
+            let restored = layout.output_state_mut(&output.output);
+            output.options.apply(restored);
+            output.toggles.apply(restored);
+        }
+        for state in &self.states {
+            let restored = layout.state_mut(&state.output, state.tags);
+            state.options.apply(restored);
+            state.toggles.apply(restored);
+        }
+    }
+}
+
+/// Location of a file that the runtime state of a `BSPLayout` is written to after every user
+/// command, so it can be restored when the layout is started again
+#[derive(Clone, Debug)]
+pub struct StateFile {
+    /// Location of the state file
+    pub path: PathBuf,
+}
+
+impl StateFile {
+    /// Create a new `StateFile` at `path`
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Location of the state file
+    pub fn new(path: PathBuf) -> StateFile {
+        StateFile { path }
+    }
+
+    /// Get the path that the state is written to when no `--state-file` option is passed
+    ///
+    /// # Returns
+    ///
+    /// `$XDG_STATE_HOME/river-bsp-layout/state.toml`, falling back to
+    /// `$HOME/.local/state/river-bsp-layout/state.toml`. `None` if neither variable is set
+    pub fn default_path() -> Option<PathBuf> {
+        let state_home = match std::env::var_os("XDG_STATE_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?)
+                .join(".local")
+                .join("state"),
+        };
+
+        Some(state_home.join("river-bsp-layout").join("state.toml"))
+    }
+
+    /// Write the changes to the output defaults and states of `layout` to the file, creating its directory if
+    /// needed
+    ///
+    /// # Arguments
+    ///
+    /// * `layout` - The layout to save
+    ///
+    /// # Errors
+    ///
+    /// Will return `BSPLayoutError::StateError` if the file cannot be written
+    pub fn save(&self, layout: &BSPLayout) -> Result<(), BSPLayoutError> {
+        let contents = toml::to_string(&SavedLayout::from(layout))
+            .map_err(|e| BSPLayoutError::StateError(e.to_string()))?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                BSPLayoutError::StateError(format!("Could not create {}: {}", dir.display(), e))
+            })?;
+        }
+
+        std::fs::write(&self.path, contents).map_err(|e| {
+            BSPLayoutError::StateError(format!("Could not write {}: {}", self.path.display(), e))
+        })
+    }
+
+    /// Replace the output defaults and states of `layout` with the ones in the file. Nothing is
+    /// changed if the file does not exist yet
+    ///
+    /// # Arguments
+    ///
+    /// * `layout` - The layout to restore the state of
+    ///
+    /// # Errors
+    ///
+    /// Will return `BSPLayoutError::StateError` if the file cannot be read or parsed, or if it
+    /// contains a split percentage that is out of range
+    pub fn restore(&self, layout: &mut BSPLayout) -> Result<(), BSPLayoutError> {
+        if !self.path.exists() {
+            return Ok(());
+        }
+
+        let contents = std::fs::read_to_string(&self.path).map_err(|e| {
+            BSPLayoutError::StateError(format!("Could not read {}: {}", self.path.display(), e))
+        })?;
+        let saved: SavedLayout =
+            toml::from_str(&contents).map_err(|e| BSPLayoutError::StateError(e.to_string()))?;
+
+        saved.validate()?;
+        saved.apply(layout);
+
+        Ok(())
+    }
+}
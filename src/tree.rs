@@ -0,0 +1,277 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::BSPLayoutError;
+
+/// Which side of a split a view or a split is on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    /// The side holding the first half of the views
+    Primary,
+
+    /// The side holding the second half of the views, plus the odd view out
+    Secondary,
+}
+
+/// Which edge of a view to move when resizing it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Direction {
+    /// The left edge
+    Left,
+
+    /// The right edge
+    Right,
+
+    /// The top edge
+    Up,
+
+    /// The bottom edge
+    Down,
+}
+
+/// A split of the BSP layout that can have its own ratio. The shape of the tree follows the view
+/// count: a split holding `n` views puts `n / 2` of them on the primary side and the rest on the
+/// secondary side, and is only made if `n` is at least 2. Nodes are created the first time their
+/// ratio is changed, and kept when the view count drops, so the ratios come back when the views do
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SplitNode {
+    /// The percentage (between 0.0 and 1.0) of space for the primary side of this split. Falls
+    /// back to `hsplit_perc` or `vsplit_perc` if unset
+    pub ratio: Option<f32>,
+
+    /// The split on the primary side
+    pub primary: Option<Box<SplitNode>>,
+
+    /// The split on the secondary side
+    pub secondary: Option<Box<SplitNode>>,
+}
+
+impl SplitNode {
+    /// Get the split on one side of this one, if it was ever created
+    ///
+    /// # Arguments
+    ///
+    /// * `side` - Which side of this split to look at
+    pub fn child(&self, side: Side) -> Option<&SplitNode> {
+        match side {
+            Side::Primary => self.primary.as_deref(),
+            Side::Secondary => self.secondary.as_deref(),
+        }
+    }
+
+    /// Get the split at `path`, if it was ever created
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Which side to take at every level, starting from this split
+    pub fn get(&self, path: &[Side]) -> Option<&SplitNode> {
+        path.iter().try_fold(self, |node, side| node.child(*side))
+    }
+
+    /// Get the split at `path`, creating it and every split on the way to it if needed
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Which side to take at every level, starting from this split
+    pub fn get_mut(&mut self, path: &[Side]) -> &mut SplitNode {
+        path.iter().fold(self, |node, side| {
+            match side {
+                Side::Primary => node.primary.get_or_insert_with(Default::default),
+                Side::Secondary => node.secondary.get_or_insert_with(Default::default),
+            }
+            .as_mut()
+        })
+    }
+
+    /// Check that the ratio of this split and of every split below it is > 0.0 and < 1.0
+    ///
+    /// # Errors
+    ///
+    /// Will return `BSPLayoutError::ConfigError` naming the path of the first split that is out
+    /// of range
+    pub fn validate(&self) -> Result<(), BSPLayoutError> {
+        self.validate_at(&mut Vec::new())
+    }
+
+    fn validate_at(&self, path: &mut Vec<Side>) -> Result<(), BSPLayoutError> {
+        if let Some(r) = self.ratio {
+            if r <= 0.0 || r >= 1.0 {
+                return Err(BSPLayoutError::ConfigError(format!(
+                    "The ratio of split {} must be > 0.0 and less than 1.0, got {}",
+                    format_path(path),
+                    r
+                )));
+            }
+        }
+
+        for side in [Side::Primary, Side::Secondary] {
+            if let Some(child) = self.child(side) {
+                path.push(side);
+                child.validate_at(path)?;
+                path.pop();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse the path of a split. The root split is `/`, and every split below it is reached by
+/// adding `p` for the primary side or `s` for the secondary side, such as `/s/p`
+///
+/// # Arguments
+///
+/// * `path` - The path to parse
+///
+/// # Errors
+///
+/// Will return `BSPLayoutError::CmdError` if the path does not start with `/` or contains
+/// anything other than `p` and `s`
+pub fn parse_path(path: &str) -> Result<Vec<Side>, BSPLayoutError> {
+    let rest = path.strip_prefix('/').ok_or_else(|| {
+        BSPLayoutError::CmdError(format!("Split paths must start with /, got {}", path))
+    })?;
+
+    rest.split('/')
+        .filter(|part| !part.is_empty())
+        .map(|part| match part {
+            "p" => Ok(Side::Primary),
+            "s" => Ok(Side::Secondary),
+            _ => Err(BSPLayoutError::CmdError(format!(
+                "Split paths may only contain p and s, got {}",
+                path
+            ))),
+        })
+        .collect()
+}
+
+/// Write `path` the way `parse_path` reads it
+///
+/// # Arguments
+///
+/// * `path` - Which side to take at every level, starting from the root split
+pub fn format_path(path: &[Side]) -> String {
+    let parts: Vec<&str> = path
+        .iter()
+        .map(|side| match side {
+            Side::Primary => "p",
+            Side::Secondary => "s",
+        })
+        .collect();
+    format!("/{}", parts.join("/"))
+}
+
+/// Get how many views the split at `path` holds
+///
+/// # Arguments
+///
+/// * `view_count` - How many views the whole layout has
+/// * `path` - Which side to take at every level, starting from the root split
+///
+/// # Returns
+///
+/// The number of views below the split, or `None` if there is no split at `path` with this many
+/// views
+pub fn views_at(view_count: u32, path: &[Side]) -> Option<u32> {
+    let mut count = view_count;
+    for side in path {
+        if count < 2 {
+            return None;
+        }
+        count = match side {
+            Side::Primary => count / 2,
+            Side::Secondary => count - count / 2,
+        };
+    }
+
+    (count >= 2).then_some(count)
+}
+
+/// Get the path of the split that directly holds the view at `index`
+///
+/// # Arguments
+///
+/// * `view_count` - How many views the whole layout has
+/// * `index` - The position of the view in the layout, starting from 0
+///
+/// # Returns
+///
+/// The path of the split, or `None` if there is no such view or it is the only one
+pub fn path_of_view(view_count: u32, index: u32) -> Option<Vec<Side>> {
+    if index >= view_count || view_count < 2 {
+        return None;
+    }
+
+    let mut path = Vec::new();
+    let (mut count, mut index) = (view_count, index);
+    loop {
+        let half = count / 2;
+        let (side, sub_count, sub_index) = if index < half {
+            (Side::Primary, half, index)
+        } else {
+            (Side::Secondary, count - half, index - half)
+        };
+
+        if sub_count == 1 {
+            return Some(path);
+        }
+        path.push(side);
+        count = sub_count;
+        index = sub_index;
+    }
+}
+
+/// Where an area of the BSP layout is in the tree of splits: the node of `split_tree` for it, if
+/// it was ever created, and how many horizontal and vertical splits it is inside of
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Branch<'a> {
+    node: Option<&'a SplitNode>,
+    hsplit_depth: usize,
+    vsplit_depth: usize,
+}
+
+impl<'a> Branch<'a> {
+    /// The branch of the whole layout
+    ///
+    /// # Arguments
+    ///
+    /// * `tree` - The root split of the layout
+    pub(crate) fn root(tree: &'a SplitNode) -> Branch<'a> {
+        Branch {
+            node: Some(tree),
+            ..Default::default()
+        }
+    }
+
+    /// The ratio of the node for this area, if it has one
+    pub(crate) fn ratio(&self) -> Option<f32> {
+        self.node.and_then(|n| n.ratio)
+    }
+
+    /// How many splits of one direction this area is inside of
+    ///
+    /// # Arguments
+    ///
+    /// * `hsplit` - Whether to count horizontal splits instead of vertical ones
+    pub(crate) fn depth(&self, hsplit: bool) -> usize {
+        if hsplit {
+            self.hsplit_depth
+        } else {
+            self.vsplit_depth
+        }
+    }
+
+    /// The branch of one side of the split of this area
+    ///
+    /// # Arguments
+    ///
+    /// * `side` - Which side of the split to go to
+    /// * `hsplit` - Whether the split of this area is horizontal
+    pub(crate) fn child(&self, side: Side, hsplit: bool) -> Branch<'a> {
+        Branch {
+            node: self.node.and_then(|n| n.child(side)),
+            hsplit_depth: self.hsplit_depth + hsplit as usize,
+            vsplit_depth: self.vsplit_depth + !hsplit as usize,
+        }
+    }
+}
@@ -1,71 +1,141 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 
-use crate::{BSPLayout, BSPLayoutError};
+use crate::gap::{Gap, GapScale, GapSide};
+use crate::grid::GridFill;
+use crate::master_stack::MainLocation;
+use crate::tree::{self, Direction, Side};
+use crate::{BSPLayout, BSPLayoutError, LayoutMode, SmartGaps, SplitDirection, StackSizing};
 
 #[derive(Parser)]
 pub struct UserCmd {
-    /// The number of pixels to pad each inner edge of a window by default.
+    /// The number of pixels to pad each inner edge of a window by default. Every gap option takes
+    /// pixels such as `10` or `10px`, or a percentage of the output such as `1%`.
     #[arg(short = 'i', long = "inner-gap", help_heading = "Inner Gap Options")]
-    pub default_inner_gap: Option<u32>,
+    pub default_inner_gap: Option<Gap>,
 
     /// The number of pixels to pad the left inner edge of each window. This Overrides
     /// `default_inner_gap`. Optional
     #[arg(long, short = 'l', help_heading = "Inner Gap Options")]
-    pub ig_left: Option<u32>,
+    pub ig_left: Option<Gap>,
 
     /// The number of pixels to pad the right inner edge of each window. This Overrides
     /// `default_inner_gap`. Optional
     #[arg(long, short = 'r', help_heading = "Inner Gap Options")]
-    pub ig_right: Option<u32>,
+    pub ig_right: Option<Gap>,
 
     /// The number of pixels to pad the bottom inner edge of each window. This Overrides
     /// `default_inner_gap`. Optional
     #[arg(long, short = 'b', help_heading = "Inner Gap Options")]
-    pub ig_bottom: Option<u32>,
+    pub ig_bottom: Option<Gap>,
 
     /// The number of pixels to pad the top inner edge of each window. This Overrides
     /// `default_inner_gap`. Optional
     #[arg(long, short = 't', help_heading = "Inner Gap Options")]
-    pub ig_top: Option<u32>,
+    pub ig_top: Option<Gap>,
 
-    /// The default size of the gap between windows and the edge of the screen.
+    /// Increase the inner gaps, or only the one on `--gap-side`, by a certain amount. The amount
+    /// must be in pixels for gaps in pixels, and a percentage for gaps that are a percentage.
+    #[arg(long, help_heading = "Inner Gap Options")]
+    pub inc_inner_gap: Option<Gap>,
+
+    /// Decrease the inner gaps, or only the one on `--gap-side`, by a certain amount, down to 0.
+    #[arg(long, help_heading = "Inner Gap Options")]
+    pub dec_inner_gap: Option<Gap>,
+
+    /// The default size of the gap between windows and the edge of the screen, in pixels such as
+    /// `10` or `10px`, or as a percentage of the output such as `1%`.
     #[arg(short = 'o', long = "outer-gap", help_heading = "Outer Gap Options")]
-    pub default_outer_gap: Option<u32>,
+    pub default_outer_gap: Option<Gap>,
 
     /// The number of pixels to place between the left screen edge and any windows. Overrides
     /// `default_outer_gap` for the left side. Optional.
     #[arg(long, short = 'L', help_heading = "Outer Gap Options")]
-    pub og_left: Option<u32>,
+    pub og_left: Option<Gap>,
 
     /// The number of pixels to place between the right screen edge and any windows. Overrides
     /// `default_outer_gap` for the right side. Optional.
     #[arg(long, short = 'R', help_heading = "Outer Gap Options")]
-    pub og_right: Option<u32>,
+    pub og_right: Option<Gap>,
 
     /// The number of pixels to place between the bottom screen edge and any windows. Overrides
     /// `default_outer_gap` for the bottom side. Optional.
     #[arg(long, short = 'B', help_heading = "Outer Gap Options")]
-    pub og_bottom: Option<u32>,
+    pub og_bottom: Option<Gap>,
 
     /// The number of pixels to place between the top screen edge and any windows. Overrides
     /// `default_outer_gap` for the top side. Optional.
     #[arg(long, short = 'T', help_heading = "Outer Gap Options")]
-    pub og_top: Option<u32>,
+    pub og_top: Option<Gap>,
+
+    /// Increase the outer gaps, or only the one on `--gap-side`, by a certain amount. The amount
+    /// must be in pixels for gaps in pixels, and a percentage for gaps that are a percentage.
+    #[arg(long, help_heading = "Outer Gap Options")]
+    pub inc_outer_gap: Option<Gap>,
+
+    /// Decrease the outer gaps, or only the one on `--gap-side`, by a certain amount, down to 0.
+    #[arg(long, help_heading = "Outer Gap Options")]
+    pub dec_outer_gap: Option<Gap>,
+
+    /// Which side `--inc-inner-gap`, `--dec-inner-gap`, `--inc-outer-gap` and `--dec-outer-gap`
+    /// change the gap on, for the inner and outer gaps alike. Without it, the gaps on every side
+    /// are changed.
+    #[arg(long, value_enum, help_heading = "Other Options")]
+    pub gap_side: Option<GapSide>,
+
+    /// Turn every inner and outer gap off, or back on. The gaps keep their sizes while they are
+    /// off, so changing them takes effect once they are turned back on.
+    #[arg(long, help_heading = "Other Options")]
+    pub toggle_gaps: bool,
+
+    /// Leave out the outer gaps while there is only one view (`single`), and also in monocle and
+    /// the inner gaps of the layouts that stack the views (`stacked`). Defaults to `single` if no value is
+    /// given.
+    #[arg(
+        long,
+        value_enum,
+        num_args = 0..=1,
+        default_missing_value = "single",
+        help_heading = "Outer Gap Options"
+    )]
+    pub smart_gaps: Option<SmartGaps>,
+
+    /// Turn smart gaps off, or back to what they were before they were turned off.
+    #[arg(long, help_heading = "Outer Gap Options")]
+    pub toggle_smart_gaps: bool,
+
+    /// Scale the inner and outer gaps by the number of views, such as `1.0,0.75,0.5@6`. Each
+    /// scale is for one more view than the one before it, starting at 1 view, unless `@` gives its
+    /// view count, and the gaps shrink evenly between two scales.
+    #[arg(long, help_heading = "Outer Gap Options")]
+    pub gap_scale: Option<GapScale>,
 
     /// The default percentage of available area that the primary window should occupy after any
-    /// split takes place.
-    #[arg(long = "split-perc", short = 's', help_heading = "Split Options")]
-    pub default_split_perc: Option<f32>,
+    /// split takes place. A comma separated list sets the percentage of each level of splits, and
+    /// the last one is used for every level below.
+    #[arg(
+        long = "split-perc",
+        short = 's',
+        value_delimiter = ',',
+        help_heading = "Split Options"
+    )]
+    pub default_split_perc: Option<Vec<f32>>,
 
     /// The percentage of available area that the primary window should occupy after a horizontal
     /// split. This will override the value of `default_split_perc` only for horizontal splits.
-    #[arg(long, short = 'H', help_heading = "Split Options")]
-    pub hsplit_perc: Option<f32>,
+    /// Takes a list like `--split-perc`.
+    #[arg(
+        long,
+        short = 'H',
+        value_delimiter = ',',
+        help_heading = "Split Options"
+    )]
+    pub hsplit_perc: Option<Vec<f32>>,
 
     /// The percentage of available area that the primary window should occupy after a vertical
     /// split. This will override the value of `default_split_perc` only for vertical splits.
-    #[arg(long, short, help_heading = "Split Options")]
-    pub vsplit_perc: Option<f32>,
+    /// Takes a list like `--split-perc`.
+    #[arg(long, short, value_delimiter = ',', help_heading = "Split Options")]
+    pub vsplit_perc: Option<Vec<f32>>,
 
     /// Set the first split to horizontal
     #[arg(long, help_heading = "Split Options")]
@@ -91,18 +161,211 @@ pub struct UserCmd {
     #[arg(long, help_heading = "Split Options")]
     pub dec_hsplit: Option<f32>,
 
+    /// Only change the split percentage of one level of splits with the inc and dec options,
+    /// counting from 0 for the first level. Without it, every level is changed.
+    #[arg(long, help_heading = "Split Options")]
+    pub depth: Option<usize>,
+
+    /// How much of the difference between the split percentage and 0.5 is kept at every level of
+    /// splits, between 0.0 and 1.0. Lower values make deeper splits more even.
+    #[arg(long, help_heading = "Split Options")]
+    pub split_decay: Option<f32>,
+
+    /// The number of pixels that the primary side of the first horizontal split occupies in the
+    /// bsp layout. 0 goes back to the hsplit percentage.
+    #[arg(long, help_heading = "Split Options")]
+    pub hsplit_px: Option<u32>,
+
+    /// The number of pixels that the primary side of the first vertical split occupies in the bsp
+    /// layout. 0 goes back to the vsplit percentage.
+    #[arg(long, help_heading = "Split Options")]
+    pub vsplit_px: Option<u32>,
+
+    /// Increase the hsplit pixels by a certain amount. Starts from the current size of the split
+    /// if no pixel size is set.
+    #[arg(long, help_heading = "Split Options")]
+    pub inc_hsplit_px: Option<u32>,
+
+    /// Increase the vsplit pixels by a certain amount. Starts from the current size of the split
+    /// if no pixel size is set.
+    #[arg(long, help_heading = "Split Options")]
+    pub inc_vsplit_px: Option<u32>,
+
+    /// Decrease the hsplit pixels by a certain amount, down to 1 pixel.
+    #[arg(long, help_heading = "Split Options")]
+    pub dec_hsplit_px: Option<u32>,
+
+    /// Decrease the vsplit pixels by a certain amount, down to 1 pixel.
+    #[arg(long, help_heading = "Split Options")]
+    pub dec_vsplit_px: Option<u32>,
+
+    /// Toggle weighting each split by the number of views on either side, so every view gets
+    /// roughly the same area.
+    #[arg(long, help_heading = "Split Options")]
+    pub equal_area: bool,
+
+    /// How the direction of each split is chosen. `alternate` switches between vertical and
+    /// horizontal splits, while `longest-side` splits each container across its longest side.
+    #[arg(long, value_enum, help_heading = "Split Options")]
+    pub split_direction: Option<SplitDirection>,
+
+    /// Only used with `--split-direction longest-side`. A container is split vertically when its
+    /// width is more than this many times its height.
+    #[arg(long, help_heading = "Split Options")]
+    pub split_bias: Option<f32>,
+
     /// Reverse the order of the views as well as the order they are added.
     #[arg(long, help_heading = "Other Options")]
     pub reverse: bool,
+
+    /// Select the split that directly holds the view at INDEX, counting from 0, for the other node
+    /// options.
+    #[arg(long, help_heading = "Node Options")]
+    pub node_view: Option<u32>,
+
+    /// Select the split at PATH for the other node options. The root split is `/`, and every
+    /// split below it is reached by adding `p` for the primary side or `s` for the secondary side,
+    /// such as `/s/p`.
+    #[arg(long, help_heading = "Node Options")]
+    pub node_path: Option<String>,
+
+    /// Set the percentage of space that the primary side of the selected split occupies, without
+    /// changing any other split.
+    #[arg(long, help_heading = "Node Options")]
+    pub node_ratio: Option<f32>,
+
+    /// Increase the ratio of the selected split by a certain amount.
+    #[arg(long, help_heading = "Node Options")]
+    pub inc_node_ratio: Option<f32>,
+
+    /// Decrease the ratio of the selected split by a certain amount.
+    #[arg(long, help_heading = "Node Options")]
+    pub dec_node_ratio: Option<f32>,
+
+    /// Make the selected split use the hsplit or vsplit percentage again.
+    #[arg(long, help_heading = "Node Options")]
+    pub reset_node: bool,
+
+    /// Make every split use the hsplit or vsplit percentage again.
+    #[arg(long, help_heading = "Node Options")]
+    pub reset_tree: bool,
+
+    /// Grow the view at INDEX, counting from 0, by moving the nearest split in `--direction`.
+    #[arg(long, help_heading = "Node Options")]
+    pub grow_view: Option<u32>,
+
+    /// Shrink the view at INDEX, counting from 0, by moving the nearest split in `--direction`
+    /// back towards it.
+    #[arg(long, help_heading = "Node Options")]
+    pub shrink_view: Option<u32>,
+
+    /// How much of its container `--grow-view` and `--shrink-view` change the view by. Defaults
+    /// to 0.05.
+    #[arg(long, help_heading = "Node Options")]
+    pub by: Option<f32>,
+
+    /// Which edge of the view `--grow-view` and `--shrink-view` move.
+    #[arg(long, value_enum, help_heading = "Node Options")]
+    pub direction: Option<Direction>,
+
+    /// The view count, usable width and usable height of the last layout of the focused output
+    /// and tags, filled in by `user_cmd`
+    #[arg(skip)]
+    pub last_layout: Option<(u32, u32, u32)>,
+
+    /// The split selected by `node_view` or `node_path`, filled in by `resolve_node`
+    #[arg(skip)]
+    pub node: Option<Vec<Side>>,
+
+    /// Set the format of the layout name that is reported to river and status bars. Put the
+    /// format in quotes if it contains spaces. `{monocle}` shows the number of views in monocle
+    /// mode, since river does not tell layouts which view is focused.
+    #[arg(long, help_heading = "Other Options")]
+    pub name_format: Option<String>,
+
+    /// Switch to a different algorithm for placing the views.
+    #[arg(long, value_enum, help_heading = "Layout Options")]
+    pub layout: Option<LayoutMode>,
+
+    /// Switch to the monocle layout, or back to the previous layout if monocle is already active.
+    #[arg(long, help_heading = "Layout Options")]
+    pub toggle_monocle: bool,
+
+    /// Switch to the next layout in the layout cycle.
+    #[arg(long, help_heading = "Layout Options")]
+    pub next_layout: bool,
+
+    /// Switch to the previous layout in the layout cycle.
+    #[arg(long, help_heading = "Layout Options")]
+    pub prev_layout: bool,
+
+    /// Set the comma separated list of layouts that `--next-layout` and `--prev-layout` cycle
+    /// through.
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        help_heading = "Layout Options"
+    )]
+    pub layout_cycle: Option<Vec<LayoutMode>>,
+
+    /// Set how many views are placed in the main area of the `master-stack` and
+    /// `centered-master` layouts.
+    #[arg(long, help_heading = "Layout Options")]
+    pub main_count: Option<u32>,
+
+    /// Increase the number of views in the main area by a certain amount.
+    #[arg(long, help_heading = "Layout Options")]
+    pub inc_main_count: Option<u32>,
+
+    /// Decrease the number of views in the main area by a certain amount.
+    #[arg(long, help_heading = "Layout Options")]
+    pub dec_main_count: Option<u32>,
+
+    /// Set the percentage of space that the main area of the `master-stack` layout occupies, or
+    /// the width of the center column of the `centered-master` layout.
+    #[arg(long, help_heading = "Layout Options")]
+    pub main_ratio: Option<f32>,
+
+    /// Increase the main ratio by a certain amount.
+    #[arg(long, help_heading = "Layout Options")]
+    pub inc_main_ratio: Option<f32>,
+
+    /// Decrease the main ratio by a certain amount.
+    #[arg(long, help_heading = "Layout Options")]
+    pub dec_main_ratio: Option<f32>,
+
+    /// Set which side of the screen the main area of the `master-stack` layout is placed on.
+    #[arg(long, value_enum, help_heading = "Layout Options")]
+    pub main_location: Option<MainLocation>,
+
+    /// Set how many columns the `grid` layout has. 0 fits the columns to the number of views.
+    #[arg(long, help_heading = "Layout Options")]
+    pub grid_cols: Option<u32>,
+
+    /// Set how many rows the `grid` layout has. 0 fits the rows to the number of views.
+    #[arg(long, help_heading = "Layout Options")]
+    pub grid_rows: Option<u32>,
+
+    /// Set what to do with the last row of the `grid` layout when there are not enough views to
+    /// fill it.
+    #[arg(long, value_enum, help_heading = "Layout Options")]
+    pub grid_fill: Option<GridFill>,
+
+    /// Set how the space is shared between the views of the `columns` and `rows` layouts.
+    #[arg(long, value_enum, help_heading = "Layout Options")]
+    pub stack_sizing: Option<StackSizing>,
+
+    /// Read the config file again, keeping the changes made with user commands. If the file
+    /// cannot be parsed, the current state is kept.
+    #[arg(long, help_heading = "Other Options")]
+    pub reload: bool,
 }
 
 impl UserCmd {
     pub fn handle_outer_gaps(&self, layout: &mut BSPLayout) {
         if let Some(g) = self.default_outer_gap {
-            layout.og_top = g;
-            layout.og_bottom = g;
-            layout.og_right = g;
-            layout.og_left = g;
+            layout.set_outer_gaps(g);
         }
         if let Some(g) = self.og_top {
             layout.og_top = g;
@@ -120,10 +383,7 @@ impl UserCmd {
 
     pub fn handle_inner_gaps(&self, layout: &mut BSPLayout) {
         if let Some(g) = self.default_inner_gap {
-            layout.ig_top = g;
-            layout.ig_bottom = g;
-            layout.ig_right = g;
-            layout.ig_left = g;
+            layout.set_inner_gaps(g);
         }
         if let Some(g) = self.ig_top {
             layout.ig_top = g;
@@ -139,36 +399,264 @@ impl UserCmd {
         }
     }
 
-    pub fn handle_ch_split(&self, layout: &mut BSPLayout) {
-        if let Some(p) = self.inc_hsplit {
-            if layout.hsplit_perc + p < 1.0 {
-                layout.hsplit_perc += p;
-            } else {
-                layout.hsplit_perc = 0.9999
+    /// Grow or shrink the gaps with `inc_inner_gap`, `dec_inner_gap`, `inc_outer_gap` and
+    /// `dec_outer_gap`, on `gap_side` or on every side
+    ///
+    /// # Errors
+    ///
+    /// Will return `BSPLayoutError::CmdError` without changing any gap if an amount does not use
+    /// the same unit as a gap it changes
+    pub fn handle_ch_gaps(&self, layout: &mut BSPLayout) -> Result<(), BSPLayoutError> {
+        let sides = match self.gap_side {
+            Some(side) => vec![side],
+            None => GapSide::value_variants().to_vec(),
+        };
+
+        let mut changed = Vec::new();
+        for (outer, amount, grow) in [
+            (false, self.inc_inner_gap, true),
+            (false, self.dec_inner_gap, false),
+            (true, self.inc_outer_gap, true),
+            (true, self.dec_outer_gap, false),
+        ] {
+            let Some(amount) = amount else {
+                continue;
+            };
+            for side in &sides {
+                let gap = if outer {
+                    layout.outer_gap_mut(*side)
+                } else {
+                    layout.inner_gap_mut(*side)
+                };
+                changed.push((outer, *side, gap.change(amount, grow).map_err(cmd_error)?));
             }
         }
-        if let Some(p) = self.inc_vsplit {
-            if layout.vsplit_perc + p < 1.0 {
-                layout.vsplit_perc += p;
+
+        for (outer, side, new_gap) in changed {
+            if outer {
+                *layout.outer_gap_mut(side) = new_gap;
             } else {
-                layout.vsplit_perc = 0.9999;
+                *layout.inner_gap_mut(side) = new_gap;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn handle_toggle_gaps(&self, layout: &mut BSPLayout) {
+        if self.toggle_gaps {
+            layout.toggle_gaps();
+        }
+    }
+
+    /// Turn `node_view` or `node_path` into the path of a split and store it in `node`, using
+    /// the view count of `last_layout`
+    ///
+    /// # Errors
+    ///
+    /// Will return `BSPLayoutError::CmdError` if both options are given, if the path cannot be
+    /// parsed, or if there is no such split in the last layout
+    pub fn resolve_node(&mut self) -> Result<(), BSPLayoutError> {
+        let view_count = self.last_layout.map(|(count, _, _)| count);
+        self.node = match (self.node_view, &self.node_path) {
+            (Some(_), Some(_)) => {
+                return Err(cmd_error(
+                    "node-view and node-path are mutually exclusive. Please select only one"
+                        .to_string(),
+                ))
+            }
+            (Some(index), None) => {
+                let count = view_count.ok_or_else(|| {
+                    cmd_error("The views of the focused tags are not known yet".to_string())
+                })?;
+                Some(tree::path_of_view(count, index).ok_or_else(|| {
+                    cmd_error(format!("There is no split holding view {}", index))
+                })?)
             }
+            (None, Some(p)) => {
+                let path = tree::parse_path(p).map_err(|e| cmd_error(e.to_string()))?;
+                if view_count.is_some_and(|count| tree::views_at(count, &path).is_none()) {
+                    return Err(cmd_error(format!("There is no split at {}", p)));
+                }
+                Some(path)
+            }
+            (None, None) => None,
+        };
+
+        Ok(())
+    }
+
+    pub fn handle_node(&self, layout: &mut BSPLayout) -> Result<(), BSPLayoutError> {
+        if self.reset_tree {
+            layout.split_tree = Default::default();
         }
 
-        if let Some(p) = self.dec_hsplit {
-            if layout.hsplit_perc - p > 0.0 {
-                layout.hsplit_perc -= p;
-            } else {
-                layout.hsplit_perc = 0.0001
+        if self.node_ratio.is_none()
+            && self.inc_node_ratio.is_none()
+            && self.dec_node_ratio.is_none()
+            && !self.reset_node
+        {
+            return Ok(());
+        }
+        let path = self.node.as_deref().ok_or_else(|| {
+            cmd_error("Select a split with node-view or node-path first".to_string())
+        })?;
+
+        if self.reset_node {
+            layout.split_tree.get_mut(path).ratio = None;
+        }
+        if let Some(r) = self.node_ratio {
+            if r <= 0.0 || r >= 1.0 {
+                return Err(cmd_error(
+                    "node-ratio must be greater than 0 and less than 1".to_string(),
+                ));
+            }
+            layout.split_tree.get_mut(path).ratio = Some(r);
+        }
+        if self.inc_node_ratio.is_none() && self.dec_node_ratio.is_none() {
+            return Ok(());
+        }
+
+        // Splits without a ratio of their own start from the one they used in the last layout
+        let mut ratio = match layout.split_tree.get(path).and_then(|n| n.ratio) {
+            Some(r) => r,
+            None => {
+                let (view_count, width, height) = self.last_layout.ok_or_else(|| {
+                    cmd_error("The views of the focused tags are not known yet".to_string())
+                })?;
+                let (_, r) = layout
+                    .split_ratio(view_count, width, height, path)
+                    .ok_or_else(|| {
+                        cmd_error(format!(
+                            "There is no split {} in the last layout",
+                            tree::format_path(path)
+                        ))
+                    })?;
+                r
             }
+        };
+        if let Some(r) = self.inc_node_ratio {
+            ratio = if ratio + r < 1.0 { ratio + r } else { 0.9999 };
         }
-        if let Some(p) = self.dec_vsplit {
-            if layout.vsplit_perc - p > 0.0 {
-                layout.vsplit_perc -= p;
+        if let Some(r) = self.dec_node_ratio {
+            ratio = if ratio - r > 0.0 { ratio - r } else { 0.0001 };
+        }
+        layout.split_tree.get_mut(path).ratio = Some(ratio);
+
+        Ok(())
+    }
+
+    pub fn handle_grow_view(&self, layout: &mut BSPLayout) -> Result<(), BSPLayoutError> {
+        let (index, amount) = match (self.grow_view, self.shrink_view) {
+            (None, None) => return Ok(()),
+            (Some(_), Some(_)) => {
+                return Err(cmd_error(
+                    "grow-view and shrink-view are mutually exclusive. Please select only one"
+                        .to_string(),
+                ))
+            }
+            (Some(i), None) => (i, self.by.unwrap_or(0.05)),
+            (None, Some(i)) => (i, -self.by.unwrap_or(0.05)),
+        };
+        let direction = self.direction.ok_or_else(|| {
+            cmd_error("Pick which edge of the view to move with direction".to_string())
+        })?;
+        let (view_count, width, height) = self.last_layout.ok_or_else(|| {
+            cmd_error("The views of the focused tags are not known yet".to_string())
+        })?;
+
+        layout
+            .grow_view(view_count, width, height, index, direction, amount)
+            .map_err(|e| match e {
+                BSPLayoutError::CmdError(msg) => cmd_error(msg),
+                e => e,
+            })
+    }
+
+    pub fn handle_ch_split(&self, layout: &mut BSPLayout) {
+        for (hsplit, inc, dec) in [
+            (true, self.inc_hsplit, self.dec_hsplit),
+            (false, self.inc_vsplit, self.dec_vsplit),
+        ] {
+            if inc.is_none() && dec.is_none() {
+                continue;
+            }
+            // The amounts can be negative, so clamp both ways to keep every split on the screen
+            for perc in depth_percs(layout, hsplit, self.depth) {
+                if let Some(p) = inc {
+                    *perc = (*perc + p).clamp(0.0001, 0.9999);
+                }
+                if let Some(p) = dec {
+                    *perc = (*perc - p).clamp(0.0001, 0.9999);
+                }
+            }
+        }
+    }
+
+    pub fn handle_split_decay(&self, layout: &mut BSPLayout) -> Result<(), BSPLayoutError> {
+        if let Some(d) = self.split_decay {
+            if !(0.0..=1.0).contains(&d) {
+                return Err(cmd_error("split-decay must be between 0 and 1".to_string()));
+            }
+            layout.split_decay = d;
+        }
+
+        Ok(())
+    }
+
+    /// Set `hsplit_px` and `vsplit_px`, or grow and shrink them. Without a pixel size, the inc
+    /// and dec options start from the size the split had in the last layout
+    ///
+    /// # Errors
+    ///
+    /// Will return `BSPLayoutError::CmdError` if the pixel size has to start from the last layout
+    /// but no layout was made for the focused tags yet
+    pub fn handle_split_px(&self, layout: &mut BSPLayout) -> Result<(), BSPLayoutError> {
+        if let Some(px) = self.hsplit_px {
+            layout.hsplit_px = px;
+        }
+        if let Some(px) = self.vsplit_px {
+            layout.vsplit_px = px;
+        }
+
+        for (hsplit, inc, dec) in [
+            (true, self.inc_hsplit_px, self.dec_hsplit_px),
+            (false, self.inc_vsplit_px, self.dec_vsplit_px),
+        ] {
+            if inc.is_none() && dec.is_none() {
+                continue;
+            }
+            let current = if hsplit {
+                layout.hsplit_px
             } else {
-                layout.vsplit_perc = 0.0001
+                layout.vsplit_px
+            };
+            let mut px = match (current, self.last_layout) {
+                (0, Some((view_count, width, height))) => {
+                    layout.first_split_length(hsplit, view_count, width, height)
+                }
+                (0, None) => {
+                    return Err(cmd_error(
+                        "The size of the split is not known until a layout was made for the focused tags"
+                            .to_string(),
+                    ))
+                }
+                (px, _) => px,
+            };
+
+            if let Some(p) = inc {
+                px = px.saturating_add(p);
+            }
+            if let Some(p) = dec {
+                px = px.saturating_sub(p).max(1);
+            }
+            if hsplit {
+                layout.hsplit_px = px;
+            } else {
+                layout.vsplit_px = px;
             }
         }
+
+        Ok(())
     }
 
     pub fn handle_start_split(&self, layout: &mut BSPLayout) -> Result<(), BSPLayoutError> {
@@ -189,16 +677,168 @@ impl UserCmd {
         Ok(())
     }
 
-    pub fn handle_set_split(&self, layout: &mut BSPLayout) {
-        if let Some(p) = self.default_split_perc {
-            layout.hsplit_perc = p;
-            layout.vsplit_perc = p;
+    /// Set the split percentages and the percentages of the deeper splits
+    ///
+    /// # Errors
+    ///
+    /// Will return `BSPLayoutError::CmdError` without changing anything if a percentage is not
+    /// greater than 0 and less than 1
+    pub fn handle_set_split(&self, layout: &mut BSPLayout) -> Result<(), BSPLayoutError> {
+        for (name, percs) in [
+            ("split-perc", &self.default_split_perc),
+            ("hsplit-perc", &self.hsplit_perc),
+            ("vsplit-perc", &self.vsplit_perc),
+        ] {
+            if let Some(p) = percs.iter().flatten().find(|p| **p <= 0.0 || **p >= 1.0) {
+                return Err(cmd_error(format!(
+                    "{} must be greater than 0 and less than 1, got {}",
+                    name, p
+                )));
+            }
+        }
+
+        if let Some([p, deeper @ ..]) = self.default_split_perc.as_deref() {
+            layout.hsplit_perc = *p;
+            layout.vsplit_perc = *p;
+            layout.deeper_hsplit_perc = deeper.to_vec();
+            layout.deeper_vsplit_perc = deeper.to_vec();
+        }
+        if let Some([p, deeper @ ..]) = self.vsplit_perc.as_deref() {
+            layout.vsplit_perc = *p;
+            layout.deeper_vsplit_perc = deeper.to_vec();
+        }
+        if let Some([p, deeper @ ..]) = self.hsplit_perc.as_deref() {
+            layout.hsplit_perc = *p;
+            layout.deeper_hsplit_perc = deeper.to_vec();
+        }
+
+        Ok(())
+    }
+
+    pub fn handle_equal_area(&self, layout: &mut BSPLayout) {
+        if self.equal_area {
+            layout.equal_area = !layout.equal_area;
+        }
+    }
+
+    pub fn handle_split_direction(&self, layout: &mut BSPLayout) -> Result<(), BSPLayoutError> {
+        if let Some(d) = self.split_direction {
+            layout.split_direction = d;
+        }
+        if let Some(b) = self.split_bias {
+            if b <= 0.0 {
+                return Err(cmd_error("split-bias must be greater than 0".to_string()));
+            }
+            layout.split_bias = b;
+        }
+
+        Ok(())
+    }
+
+    pub fn handle_layout(&self, layout: &mut BSPLayout) {
+        match self.layout {
+            // Going through the toggle remembers the mode to return to
+            Some(LayoutMode::Monocle) if layout.mode != LayoutMode::Monocle => {
+                layout.toggle_monocle()
+            }
+            Some(m) => layout.mode = m,
+            None => (),
         }
-        if let Some(p) = self.vsplit_perc {
-            layout.vsplit_perc = p;
+
+        if self.toggle_monocle {
+            layout.toggle_monocle();
+        }
+
+        if let Some(c) = &self.layout_cycle {
+            if !c.is_empty() {
+                layout.layout_cycle = c.clone();
+            }
+        }
+        if self.next_layout {
+            layout.cycle_layout(true);
         }
-        if let Some(p) = self.hsplit_perc {
-            layout.hsplit_perc = p;
+        if self.prev_layout {
+            layout.cycle_layout(false);
+        }
+    }
+
+    pub fn handle_main(&self, layout: &mut BSPLayout) -> Result<(), BSPLayoutError> {
+        if let Some(c) = self.main_count {
+            layout.main_count = c;
+        }
+        if let Some(c) = self.inc_main_count {
+            layout.main_count = layout.main_count.saturating_add(c);
+        }
+        if let Some(c) = self.dec_main_count {
+            layout.main_count = layout.main_count.saturating_sub(c);
+        }
+
+        if let Some(r) = self.main_ratio {
+            if r <= 0.0 || r >= 1.0 {
+                return Err(cmd_error(
+                    "main-ratio must be greater than 0 and less than 1".to_string(),
+                ));
+            }
+            layout.main_ratio = r;
+        }
+        if let Some(r) = self.inc_main_ratio {
+            if layout.main_ratio + r < 1.0 {
+                layout.main_ratio += r;
+            } else {
+                layout.main_ratio = 0.9999;
+            }
+        }
+        if let Some(r) = self.dec_main_ratio {
+            if layout.main_ratio - r > 0.0 {
+                layout.main_ratio -= r;
+            } else {
+                layout.main_ratio = 0.0001;
+            }
+        }
+
+        if let Some(l) = self.main_location {
+            layout.main_location = l;
+        }
+
+        Ok(())
+    }
+
+    pub fn handle_grid(&self, layout: &mut BSPLayout) {
+        if let Some(c) = self.grid_cols {
+            layout.grid_cols = c;
+        }
+        if let Some(r) = self.grid_rows {
+            layout.grid_rows = r;
+        }
+        if let Some(f) = self.grid_fill {
+            layout.grid_fill = f;
+        }
+    }
+
+    pub fn handle_stack_sizing(&self, layout: &mut BSPLayout) {
+        if let Some(s) = self.stack_sizing {
+            layout.stack_sizing = s;
+        }
+    }
+
+    pub fn handle_name_format(&self, layout: &mut BSPLayout) {
+        if let Some(f) = &self.name_format {
+            layout.name_format = f.clone();
+        }
+    }
+
+    pub fn handle_smart_gaps(&self, layout: &mut BSPLayout) {
+        if let Some(g) = self.smart_gaps {
+            layout.smart_gaps = g;
+        }
+        if self.toggle_smart_gaps {
+            layout.toggle_smart_gaps();
+        }
+    }
+
+    pub fn handle_gap_scale(&self, layout: &mut BSPLayout) {
+        if let Some(s) = &self.gap_scale {
+            layout.gap_scale = s.clone();
         }
     }
 
@@ -208,3 +848,75 @@ impl UserCmd {
         }
     }
 }
+
+/// Get the horizontal or vertical split percentages that the inc and dec options change: the one
+/// used at `depth`, or every one if no depth was given. The deeper list is padded with its last
+/// entry first, so changing one level does not change the levels below it
+///
+/// # Arguments
+///
+/// * `layout` - The layout to change
+/// * `hsplit` - Whether to get the hsplit percentages instead of the vsplit ones
+/// * `depth` - The level of splits to change, counting from 0
+fn depth_percs(layout: &mut BSPLayout, hsplit: bool, depth: Option<usize>) -> Vec<&mut f32> {
+    let (perc, deeper) = if hsplit {
+        (&mut layout.hsplit_perc, &mut layout.deeper_hsplit_perc)
+    } else {
+        (&mut layout.vsplit_perc, &mut layout.deeper_vsplit_perc)
+    };
+
+    match depth {
+        None => std::iter::once(perc).chain(deeper.iter_mut()).collect(),
+        Some(d) => {
+            if deeper.len() <= d {
+                let last = deeper.last().copied().unwrap_or(*perc);
+                deeper.resize(d + 1, last);
+            }
+            match d.checked_sub(1) {
+                Some(i) => vec![&mut deeper[i]],
+                None => vec![perc],
+            }
+        }
+    }
+}
+
+/// Split a user command into its arguments at whitespace, the way a shell would. Single or double
+/// quotes keep the whitespace inside them, and a backslash keeps the next character as it is, so
+/// `--name-format '{mode} {count}'` passes the format as one argument. A quote that is never
+/// closed runs to the end of the command
+///
+/// # Arguments
+///
+/// * `cmd` - The user command as river sends it
+pub(crate) fn split_args(cmd: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg: Option<String> = None;
+    let mut quote = None;
+    let mut chars = cmd.chars();
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', Some('\'')) => arg.get_or_insert_with(String::new).push(c),
+            ('\\', _) => {
+                let arg = arg.get_or_insert_with(String::new);
+                arg.extend(chars.next());
+            }
+            (c, Some(q)) if c == q => quote = None,
+            (c, Some(_)) => arg.get_or_insert_with(String::new).push(c),
+            ('\'' | '"', None) => {
+                arg.get_or_insert_with(String::new);
+                quote = Some(c);
+            }
+            (c, None) if c.is_whitespace() => args.extend(arg.take()),
+            (c, None) => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(arg);
+
+    args
+}
+
+/// Print `msg` and turn it into a `BSPLayoutError::CmdError`
+fn cmd_error(msg: String) -> BSPLayoutError {
+    eprintln!("{}", msg);
+    BSPLayoutError::CmdError(msg)
+}
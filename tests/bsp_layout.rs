@@ -1,4 +1,9 @@
-use river_bsp_layout::BSPLayout;
+use clap::ValueEnum;
+use river_bsp_layout::gap::Gap;
+use river_bsp_layout::grid::GridFill;
+use river_bsp_layout::master_stack::MainLocation;
+use river_bsp_layout::tree::SplitNode;
+use river_bsp_layout::{BSPLayout, LayoutMode, SmartGaps, SplitDirection, StackSizing};
 use river_layout_toolkit::Layout;
 
 #[test]
@@ -9,7 +14,7 @@ fn test_handle_layout_helper_one_container() {
     let layout = bsp.generate_layout(1, 1920, 1080, 1, "").unwrap();
 
     assert_eq!(layout.views.len(), 1);
-    let first_view = layout.views.get(0).unwrap();
+    let first_view = layout.views.first().unwrap();
     assert_eq!(
         (
             first_view.x,
@@ -29,7 +34,7 @@ fn test_handle_layout_helper_two_containers() {
     let layout = bsp.generate_layout(2, 1920, 1080, 1, "").unwrap();
 
     assert_eq!(layout.views.len(), 2);
-    let first_view = layout.views.get(0).unwrap();
+    let first_view = layout.views.first().unwrap();
     assert_eq!(
         (
             first_view.x,
@@ -60,7 +65,7 @@ fn test_handle_layout_helper_three_containers() {
     let layout = bsp.generate_layout(3, 1920, 1080, 1, "").unwrap();
 
     assert_eq!(layout.views.len(), 3);
-    let first_view = layout.views.get(0).unwrap();
+    let first_view = layout.views.first().unwrap();
     assert_eq!(
         (
             first_view.x,
@@ -102,7 +107,7 @@ fn test_handle_layout_helper_four_containers() {
     let layout = bsp.generate_layout(4, 1920, 1080, 1, "").unwrap();
 
     assert_eq!(layout.views.len(), 4);
-    let first_view = layout.views.get(0).unwrap();
+    let first_view = layout.views.first().unwrap();
     assert_eq!(
         (
             first_view.x,
@@ -155,7 +160,7 @@ fn test_generate_layout_no_gaps() {
     let layout = bsp.generate_layout(4, 1920, 1080, 1, "eDP-1").unwrap();
 
     assert_eq!(layout.views.len(), 4);
-    let first_view = layout.views.get(0).unwrap();
+    let first_view = layout.views.first().unwrap();
     assert_eq!(
         (
             first_view.x,
@@ -204,12 +209,12 @@ fn test_generate_layout_no_gaps() {
 fn test_generate_layout_with_gaps() {
     let mut bsp = BSPLayout::new();
     bsp.set_all_outer_gaps(10);
-    bsp.og_top = 0;
+    bsp.og_top = Gap::Px(0);
     bsp.set_all_inner_gaps(10);
     let layout = bsp.generate_layout(4, 1920, 1080, 1, "eDP-1").unwrap();
 
     assert_eq!(layout.views.len(), 4);
-    let first_view = layout.views.get(0).unwrap();
+    let first_view = layout.views.first().unwrap();
     assert_eq!(
         (
             first_view.x,
@@ -266,7 +271,7 @@ fn test_generate_layout_split() {
     bsp.set_all_inner_gaps(0);
     let layout = bsp.generate_layout(4, 1920, 1080, 1, "eDP-1").unwrap();
 
-    let first_view = layout.views.get(0).unwrap();
+    let first_view = layout.views.first().unwrap();
     assert_eq!(
         (
             first_view.x,
@@ -320,7 +325,7 @@ fn test_generate_layout_reverse() {
     let layout = bsp.generate_layout(3, 1920, 1080, 1, "eDP-1").unwrap();
 
     assert_eq!(layout.views.len(), 3);
-    let first_view = layout.views.get(0).unwrap();
+    let first_view = layout.views.first().unwrap();
     assert_eq!(
         (
             first_view.x,
@@ -353,3 +358,1145 @@ fn test_generate_layout_reverse() {
         (0, 0, 960, 540)
     );
 }
+
+#[test]
+fn test_generate_layout_per_tag() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_inner_gaps(0);
+    bsp.set_all_outer_gaps(0);
+    bsp.user_cmd("--start-hsplit".to_string(), Some(2), "eDP-1")
+        .unwrap();
+
+    let layout = bsp.generate_layout(2, 1920, 1080, 1, "eDP-1").unwrap();
+    let second_view = layout.views.get(1).unwrap();
+    assert_eq!(
+        (
+            second_view.x,
+            second_view.y,
+            second_view.width,
+            second_view.height
+        ),
+        (960, 0, 960, 1080)
+    );
+
+    let layout = bsp.generate_layout(2, 1920, 1080, 2, "eDP-1").unwrap();
+    let second_view = layout.views.get(1).unwrap();
+    assert_eq!(
+        (
+            second_view.x,
+            second_view.y,
+            second_view.width,
+            second_view.height
+        ),
+        (0, 540, 1920, 540)
+    );
+}
+
+#[test]
+fn test_generate_layout_per_output() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_inner_gaps(0);
+    bsp.set_all_outer_gaps(0);
+    bsp.user_cmd("--outer-gap 10".to_string(), Some(1), "DP-2")
+        .unwrap();
+
+    let layout = bsp.generate_layout(1, 1920, 1080, 1, "eDP-1").unwrap();
+    let first_view = layout.views.first().unwrap();
+    assert_eq!(
+        (
+            first_view.x,
+            first_view.y,
+            first_view.width,
+            first_view.height
+        ),
+        (0, 0, 1920, 1080)
+    );
+
+    let layout = bsp.generate_layout(1, 1920, 1080, 1, "DP-2").unwrap();
+    let first_view = layout.views.first().unwrap();
+    assert_eq!(
+        (
+            first_view.x,
+            first_view.y,
+            first_view.width,
+            first_view.height
+        ),
+        (10, 10, 1900, 1060)
+    );
+}
+
+#[test]
+fn test_generate_layout_equal_area() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_inner_gaps(0);
+    bsp.set_all_outer_gaps(0);
+    bsp.equal_area = true;
+    let layout = bsp.generate_layout(3, 1920, 1080, 1, "eDP-1").unwrap();
+
+    assert_eq!(layout.views.len(), 3);
+    let first_view = layout.views.first().unwrap();
+    assert_eq!(
+        (
+            first_view.x,
+            first_view.y,
+            first_view.width,
+            first_view.height
+        ),
+        (0, 0, 640, 1080)
+    );
+
+    let second_view = layout.views.get(1).unwrap();
+    assert_eq!(
+        (
+            second_view.x,
+            second_view.y,
+            second_view.width,
+            second_view.height
+        ),
+        (640, 0, 1280, 540)
+    );
+
+    let third_view = layout.views.get(2).unwrap();
+    assert_eq!(
+        (
+            third_view.x,
+            third_view.y,
+            third_view.width,
+            third_view.height
+        ),
+        (640, 540, 1280, 540)
+    );
+
+    // With an even number of views on each side, the split percentage is used as is
+    bsp.vsplit_perc = 0.6;
+    let layout = bsp.generate_layout(2, 1920, 1080, 1, "eDP-1").unwrap();
+    let first_view = layout.views.first().unwrap();
+    assert_eq!(
+        (
+            first_view.x,
+            first_view.y,
+            first_view.width,
+            first_view.height
+        ),
+        (0, 0, 1152, 1080)
+    );
+}
+
+#[test]
+fn test_generate_layout_longest_side() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_inner_gaps(0);
+    bsp.set_all_outer_gaps(0);
+    bsp.split_direction = SplitDirection::LongestSide;
+    let layout = bsp.generate_layout(3, 1080, 1920, 1, "DP-2").unwrap();
+
+    assert_eq!(layout.views.len(), 3);
+    let first_view = layout.views.first().unwrap();
+    assert_eq!(
+        (
+            first_view.x,
+            first_view.y,
+            first_view.width,
+            first_view.height
+        ),
+        (0, 0, 1080, 960)
+    );
+
+    let second_view = layout.views.get(1).unwrap();
+    assert_eq!(
+        (
+            second_view.x,
+            second_view.y,
+            second_view.width,
+            second_view.height
+        ),
+        (0, 960, 540, 960)
+    );
+
+    let third_view = layout.views.get(2).unwrap();
+    assert_eq!(
+        (
+            third_view.x,
+            third_view.y,
+            third_view.width,
+            third_view.height
+        ),
+        (540, 960, 540, 960)
+    );
+
+    // A bias of 2 means the width has to be more than twice the height for a vertical split
+    bsp.split_bias = 2.0;
+    let layout = bsp.generate_layout(2, 1920, 1080, 1, "eDP-1").unwrap();
+    let second_view = layout.views.get(1).unwrap();
+    assert_eq!(
+        (
+            second_view.x,
+            second_view.y,
+            second_view.width,
+            second_view.height
+        ),
+        (0, 540, 1920, 540)
+    );
+}
+
+#[test]
+fn test_generate_layout_master_stack() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_inner_gaps(0);
+    bsp.set_all_outer_gaps(0);
+    bsp.mode = LayoutMode::MasterStack;
+    let layout = bsp.generate_layout(3, 1920, 1080, 1, "eDP-1").unwrap();
+
+    assert_eq!(layout.views.len(), 3);
+    let first_view = layout.views.first().unwrap();
+    assert_eq!(
+        (
+            first_view.x,
+            first_view.y,
+            first_view.width,
+            first_view.height
+        ),
+        (0, 0, 1152, 1080)
+    );
+
+    let second_view = layout.views.get(1).unwrap();
+    assert_eq!(
+        (
+            second_view.x,
+            second_view.y,
+            second_view.width,
+            second_view.height
+        ),
+        (1152, 0, 768, 540)
+    );
+
+    let third_view = layout.views.get(2).unwrap();
+    assert_eq!(
+        (
+            third_view.x,
+            third_view.y,
+            third_view.width,
+            third_view.height
+        ),
+        (1152, 540, 768, 540)
+    );
+
+    // Two main views on the bottom with inner gaps between every view
+    bsp.set_all_inner_gaps(10);
+    bsp.main_count = 2;
+    bsp.main_ratio = 0.5;
+    bsp.main_location = MainLocation::Bottom;
+    let layout = bsp.generate_layout(3, 1920, 1080, 1, "eDP-1").unwrap();
+
+    let first_view = layout.views.first().unwrap();
+    assert_eq!(
+        (
+            first_view.x,
+            first_view.y,
+            first_view.width,
+            first_view.height
+        ),
+        (0, 550, 950, 530)
+    );
+
+    let second_view = layout.views.get(1).unwrap();
+    assert_eq!(
+        (
+            second_view.x,
+            second_view.y,
+            second_view.width,
+            second_view.height
+        ),
+        (970, 550, 950, 530)
+    );
+
+    let third_view = layout.views.get(2).unwrap();
+    assert_eq!(
+        (
+            third_view.x,
+            third_view.y,
+            third_view.width,
+            third_view.height
+        ),
+        (0, 0, 1920, 530)
+    );
+
+    // Every view fits in the main area, so it takes up all of the space
+    let layout = bsp.generate_layout(2, 1920, 1080, 1, "eDP-1").unwrap();
+    let second_view = layout.views.get(1).unwrap();
+    assert_eq!(
+        (
+            second_view.x,
+            second_view.y,
+            second_view.width,
+            second_view.height
+        ),
+        (970, 0, 950, 1080)
+    );
+
+    bsp.main_ratio = 1.0;
+    assert!(bsp.generate_layout(3, 1920, 1080, 1, "eDP-1").is_err());
+}
+
+#[test]
+fn test_generate_layout_monocle() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_outer_gaps(10);
+    bsp.mode = LayoutMode::Monocle;
+    let layout = bsp.generate_layout(3, 1920, 1080, 1, "eDP-1").unwrap();
+
+    assert_eq!(layout.views.len(), 3);
+    for view in layout.views {
+        assert_eq!(
+            (view.x, view.y, view.width, view.height),
+            (10, 10, 1900, 1060)
+        );
+    }
+}
+
+#[test]
+fn test_generate_layout_dwindle() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_inner_gaps(0);
+    bsp.set_all_outer_gaps(0);
+    bsp.mode = LayoutMode::Dwindle;
+    let layout = bsp.generate_layout(4, 1920, 1080, 1, "eDP-1").unwrap();
+
+    assert_eq!(layout.views.len(), 4);
+    let first_view = layout.views.first().unwrap();
+    assert_eq!(
+        (
+            first_view.x,
+            first_view.y,
+            first_view.width,
+            first_view.height
+        ),
+        (0, 0, 960, 1080)
+    );
+
+    let second_view = layout.views.get(1).unwrap();
+    assert_eq!(
+        (
+            second_view.x,
+            second_view.y,
+            second_view.width,
+            second_view.height
+        ),
+        (960, 0, 960, 540)
+    );
+
+    let third_view = layout.views.get(2).unwrap();
+    assert_eq!(
+        (
+            third_view.x,
+            third_view.y,
+            third_view.width,
+            third_view.height
+        ),
+        (960, 540, 480, 540)
+    );
+
+    let fourth_view = layout.views.get(3).unwrap();
+    assert_eq!(
+        (
+            fourth_view.x,
+            fourth_view.y,
+            fourth_view.width,
+            fourth_view.height
+        ),
+        (1440, 540, 480, 540)
+    );
+}
+
+#[test]
+fn test_generate_layout_spiral() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_inner_gaps(0);
+    bsp.set_all_outer_gaps(0);
+    bsp.mode = LayoutMode::Spiral;
+    let layout = bsp.generate_layout(5, 1920, 1080, 1, "eDP-1").unwrap();
+
+    assert_eq!(layout.views.len(), 5);
+    let first_view = layout.views.first().unwrap();
+    assert_eq!(
+        (
+            first_view.x,
+            first_view.y,
+            first_view.width,
+            first_view.height
+        ),
+        (0, 0, 960, 1080)
+    );
+
+    let second_view = layout.views.get(1).unwrap();
+    assert_eq!(
+        (
+            second_view.x,
+            second_view.y,
+            second_view.width,
+            second_view.height
+        ),
+        (960, 0, 960, 540)
+    );
+
+    let third_view = layout.views.get(2).unwrap();
+    assert_eq!(
+        (
+            third_view.x,
+            third_view.y,
+            third_view.width,
+            third_view.height
+        ),
+        (1440, 540, 480, 540)
+    );
+
+    let fourth_view = layout.views.get(3).unwrap();
+    assert_eq!(
+        (
+            fourth_view.x,
+            fourth_view.y,
+            fourth_view.width,
+            fourth_view.height
+        ),
+        (960, 810, 480, 270)
+    );
+
+    let fifth_view = layout.views.get(4).unwrap();
+    assert_eq!(
+        (
+            fifth_view.x,
+            fifth_view.y,
+            fifth_view.width,
+            fifth_view.height
+        ),
+        (960, 540, 480, 270)
+    );
+
+    // Reversing flips the side each new view is placed on
+    bsp.reversed = true;
+    let layout = bsp.generate_layout(3, 1920, 1080, 1, "eDP-1").unwrap();
+    let first_view = layout.views.first().unwrap();
+    assert_eq!(
+        (
+            first_view.x,
+            first_view.y,
+            first_view.width,
+            first_view.height
+        ),
+        (960, 0, 960, 1080)
+    );
+
+    let second_view = layout.views.get(1).unwrap();
+    assert_eq!(
+        (
+            second_view.x,
+            second_view.y,
+            second_view.width,
+            second_view.height
+        ),
+        (0, 540, 960, 540)
+    );
+
+    let third_view = layout.views.get(2).unwrap();
+    assert_eq!(
+        (
+            third_view.x,
+            third_view.y,
+            third_view.width,
+            third_view.height
+        ),
+        (0, 0, 960, 540)
+    );
+}
+
+#[test]
+fn test_generate_layout_grid() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_inner_gaps(0);
+    bsp.set_all_outer_gaps(0);
+    bsp.mode = LayoutMode::Grid;
+    let layout = bsp.generate_layout(6, 1920, 1080, 1, "eDP-1").unwrap();
+
+    // Six views fit in a 3x2 grid of equally sized cells
+    assert_eq!(layout.views.len(), 6);
+    let cells: Vec<_> = layout
+        .views
+        .iter()
+        .map(|v| (v.x, v.y, v.width, v.height))
+        .collect();
+    assert_eq!(
+        cells,
+        vec![
+            (0, 0, 640, 540),
+            (640, 0, 640, 540),
+            (1280, 0, 640, 540),
+            (0, 540, 640, 540),
+            (640, 540, 640, 540),
+            (1280, 540, 640, 540),
+        ]
+    );
+
+    // The incomplete last row is stretched by default
+    let layout = bsp.generate_layout(5, 1920, 1080, 1, "eDP-1").unwrap();
+    let fifth_view = layout.views.get(4).unwrap();
+    assert_eq!(
+        (
+            fifth_view.x,
+            fifth_view.y,
+            fifth_view.width,
+            fifth_view.height
+        ),
+        (960, 540, 960, 540)
+    );
+
+    bsp.grid_fill = GridFill::Center;
+    let layout = bsp.generate_layout(5, 1920, 1080, 1, "eDP-1").unwrap();
+    let fourth_view = layout.views.get(3).unwrap();
+    assert_eq!(
+        (
+            fourth_view.x,
+            fourth_view.y,
+            fourth_view.width,
+            fourth_view.height
+        ),
+        (320, 540, 640, 540)
+    );
+
+    let fifth_view = layout.views.get(4).unwrap();
+    assert_eq!(
+        (
+            fifth_view.x,
+            fifth_view.y,
+            fifth_view.width,
+            fifth_view.height
+        ),
+        (960, 540, 640, 540)
+    );
+
+    // Explicit columns add rows as needed, and inner gaps go between the cells
+    bsp.set_all_inner_gaps(10);
+    bsp.grid_cols = 1;
+    let layout = bsp.generate_layout(2, 1920, 1080, 1, "eDP-1").unwrap();
+    let first_view = layout.views.first().unwrap();
+    assert_eq!(
+        (
+            first_view.x,
+            first_view.y,
+            first_view.width,
+            first_view.height
+        ),
+        (0, 0, 1920, 530)
+    );
+
+    let second_view = layout.views.get(1).unwrap();
+    assert_eq!(
+        (
+            second_view.x,
+            second_view.y,
+            second_view.width,
+            second_view.height
+        ),
+        (0, 550, 1920, 530)
+    );
+}
+
+#[test]
+fn test_generate_layout_grid_huge_size() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_inner_gaps(0);
+    bsp.set_all_outer_gaps(0);
+    bsp.mode = LayoutMode::Grid;
+
+    // The columns are limited to the number of views instead of being allocated
+    bsp.grid_cols = u32::MAX;
+    for fill in [GridFill::Stretch, GridFill::Center] {
+        bsp.grid_fill = fill;
+        let layout = bsp.generate_layout(3, 1920, 1080, 1, "eDP-1").unwrap();
+        let cells: Vec<_> = layout
+            .views
+            .iter()
+            .map(|v| (v.x, v.y, v.width, v.height))
+            .collect();
+        assert_eq!(
+            cells,
+            vec![(0, 0, 640, 1080), (640, 0, 640, 1080), (1280, 0, 640, 1080)]
+        );
+    }
+}
+
+#[test]
+fn test_generate_layout_centered_master() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_inner_gaps(0);
+    bsp.set_all_outer_gaps(0);
+    bsp.mode = LayoutMode::CenteredMaster;
+    bsp.main_ratio = 0.5;
+    bsp.split_direction = SplitDirection::LongestSide;
+    let layout = bsp.generate_layout(4, 5120, 1440, 1, "DP-2").unwrap();
+
+    // The stack alternates between the right and the left of the center column
+    assert_eq!(layout.views.len(), 4);
+    let cells: Vec<_> = layout
+        .views
+        .iter()
+        .map(|v| (v.x, v.y, v.width, v.height))
+        .collect();
+    assert_eq!(
+        cells,
+        vec![
+            (1280, 0, 2560, 1440),
+            (3840, 0, 1280, 720),
+            (0, 0, 1280, 1440),
+            (3840, 720, 1280, 720),
+        ]
+    );
+
+    // With only one stacked view, the main column moves to the left
+    let layout = bsp.generate_layout(2, 5120, 1440, 1, "DP-2").unwrap();
+    let first_view = layout.views.first().unwrap();
+    assert_eq!(
+        (
+            first_view.x,
+            first_view.y,
+            first_view.width,
+            first_view.height
+        ),
+        (0, 0, 2560, 1440)
+    );
+
+    let second_view = layout.views.get(1).unwrap();
+    assert_eq!(
+        (
+            second_view.x,
+            second_view.y,
+            second_view.width,
+            second_view.height
+        ),
+        (2560, 0, 2560, 1440)
+    );
+
+    // A single view takes up the whole screen
+    let layout = bsp.generate_layout(1, 5120, 1440, 1, "DP-2").unwrap();
+    let first_view = layout.views.first().unwrap();
+    assert_eq!(
+        (
+            first_view.x,
+            first_view.y,
+            first_view.width,
+            first_view.height
+        ),
+        (0, 0, 5120, 1440)
+    );
+}
+
+#[test]
+fn test_generate_layout_columns() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_inner_gaps(10);
+    bsp.set_all_outer_gaps(0);
+    bsp.mode = LayoutMode::Columns;
+    let layout = bsp.generate_layout(3, 1920, 1080, 1, "eDP-1").unwrap();
+
+    assert_eq!(layout.views.len(), 3);
+    let cells: Vec<_> = layout
+        .views
+        .iter()
+        .map(|v| (v.x, v.y, v.width, v.height))
+        .collect();
+    assert_eq!(
+        cells,
+        vec![(0, 0, 630, 1080), (650, 0, 620, 1080), (1290, 0, 630, 1080)]
+    );
+}
+
+#[test]
+fn test_generate_layout_rows() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_inner_gaps(0);
+    bsp.set_all_outer_gaps(0);
+    bsp.mode = LayoutMode::Rows;
+    bsp.stack_sizing = StackSizing::Geometric;
+    let layout = bsp.generate_layout(3, 1920, 1080, 1, "eDP-1").unwrap();
+
+    // Each view gets half of the space left by the views before it
+    assert_eq!(layout.views.len(), 3);
+    let cells: Vec<_> = layout
+        .views
+        .iter()
+        .map(|v| (v.x, v.y, v.width, v.height))
+        .collect();
+    assert_eq!(
+        cells,
+        vec![(0, 0, 1920, 540), (0, 540, 1920, 270), (0, 810, 1920, 270)]
+    );
+
+    bsp.reversed = true;
+    let layout = bsp.generate_layout(3, 1920, 1080, 1, "eDP-1").unwrap();
+    let cells: Vec<_> = layout
+        .views
+        .iter()
+        .map(|v| (v.x, v.y, v.width, v.height))
+        .collect();
+    assert_eq!(
+        cells,
+        vec![(0, 540, 1920, 540), (0, 270, 1920, 270), (0, 0, 1920, 270)]
+    );
+}
+
+#[test]
+fn test_generate_layout_geometric_many_views() {
+    let mut bsp = BSPLayout::new();
+    bsp.stack_sizing = StackSizing::Geometric;
+    bsp.vsplit_perc = 0.9;
+    bsp.hsplit_perc = 0.9;
+    for (mode, inner_gap, outer_gap) in [
+        (LayoutMode::Columns, 0, 0),
+        (LayoutMode::Rows, 0, 0),
+        (LayoutMode::Columns, 5, 10),
+        (LayoutMode::Rows, 5, 10),
+    ] {
+        bsp.mode = mode;
+        bsp.set_all_inner_gaps(inner_gap);
+        bsp.set_all_outer_gaps(outer_gap);
+        let layout = bsp.generate_layout(12, 1920, 1080, 1, "eDP-1").unwrap();
+
+        // The views at the end still get a pixel of their own, instead of falling off the output
+        assert_eq!(layout.views.len(), 12);
+        for v in &layout.views {
+            assert!(v.width > 0 && v.height > 0);
+            assert!(v.x >= 0 && v.x + v.width as i32 <= 1920, "{:?}", v);
+            assert!(v.y >= 0 && v.y + v.height as i32 <= 1080, "{:?}", v);
+        }
+        let mut positions: Vec<_> = layout.views.iter().map(|v| (v.x, v.y)).collect();
+        positions.dedup();
+        assert_eq!(positions.len(), 12);
+    }
+}
+
+#[test]
+fn test_generate_layout_name() {
+    let mut bsp = BSPLayout::new();
+    let layout = bsp.generate_layout(2, 1920, 1080, 1, "eDP-1").unwrap();
+    assert_eq!(layout.layout_name, "bsp");
+
+    bsp.mode = LayoutMode::CenteredMaster;
+    let layout = bsp.generate_layout(2, 1920, 1080, 1, "eDP-1").unwrap();
+    assert_eq!(layout.layout_name, "centered-master");
+
+    bsp.mode = LayoutMode::Bsp;
+    bsp.vsplit_perc = 0.6;
+    bsp.name_format = "{mode} {count} [{vsplit}%] {reversed}".to_string();
+    let layout = bsp.generate_layout(3, 1920, 1080, 1, "eDP-1").unwrap();
+    assert_eq!(layout.layout_name, "bsp 3 [60%]");
+
+    bsp.reversed = true;
+    let layout = bsp.generate_layout(3, 1920, 1080, 1, "eDP-1").unwrap();
+    assert_eq!(layout.layout_name, "bsp 3 [60%] ⇄");
+
+    bsp.mode = LayoutMode::Monocle;
+    bsp.name_format = "{mode}{monocle} {start} {hsplit}\0".to_string();
+    let layout = bsp.generate_layout(4, 1920, 1080, 1, "eDP-1").unwrap();
+    assert_eq!(layout.layout_name, "monocle[4] v 50");
+}
+
+#[test]
+fn test_generate_layout_split_tree() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_inner_gaps(0);
+    bsp.set_all_outer_gaps(0);
+    bsp.split_tree.ratio = Some(0.75);
+    bsp.split_tree.secondary = Some(Box::new(SplitNode {
+        ratio: Some(0.25),
+        ..Default::default()
+    }));
+    let layout = bsp.generate_layout(4, 1920, 1080, 1, "eDP-1").unwrap();
+
+    // Only the root and its secondary split are changed, the primary split stays even
+    assert_eq!(layout.views.len(), 4);
+    let cells: Vec<_> = layout
+        .views
+        .iter()
+        .map(|v| (v.x, v.y, v.width, v.height))
+        .collect();
+    assert_eq!(
+        cells,
+        vec![
+            (0, 0, 1440, 540),
+            (0, 540, 1440, 540),
+            (1440, 0, 480, 270),
+            (1440, 270, 480, 810),
+        ]
+    );
+}
+
+#[test]
+fn test_generate_layout_deeper_split_perc() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_inner_gaps(0);
+    bsp.set_all_outer_gaps(0);
+    bsp.vsplit_perc = 0.75;
+    bsp.deeper_vsplit_perc = vec![0.25];
+    assert_eq!(bsp.depth_perc(false, 0), 0.75);
+    assert_eq!(bsp.depth_perc(false, 1), 0.25);
+    assert_eq!(bsp.depth_perc(false, 5), 0.25);
+    assert_eq!(bsp.depth_perc(true, 5), 0.5);
+    let layout = bsp.generate_layout(8, 1920, 1080, 1, "eDP-1").unwrap();
+
+    // Only the first vertical split uses 0.75, the ones inside it use 0.25
+    assert_eq!(layout.views.len(), 8);
+    let cells: Vec<_> = layout
+        .views
+        .iter()
+        .map(|v| (v.x, v.y, v.width, v.height))
+        .collect();
+    assert_eq!(
+        cells,
+        vec![
+            (0, 0, 360, 540),
+            (360, 0, 1080, 540),
+            (0, 540, 360, 540),
+            (360, 540, 1080, 540),
+            (1440, 0, 120, 540),
+            (1560, 0, 360, 540),
+            (1440, 540, 120, 540),
+            (1560, 540, 360, 540),
+        ]
+    );
+
+    bsp.deeper_vsplit_perc = vec![1.0];
+    assert!(bsp.generate_layout(8, 1920, 1080, 1, "eDP-1").is_err());
+}
+
+#[test]
+fn test_generate_layout_split_decay() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_inner_gaps(0);
+    bsp.set_all_outer_gaps(0);
+    bsp.vsplit_perc = 0.75;
+    bsp.split_decay = 0.5;
+    assert_eq!(bsp.depth_perc(false, 0), 0.75);
+    assert_eq!(bsp.depth_perc(false, 1), 0.625);
+    assert_eq!(bsp.depth_perc(false, 2), 0.5625);
+    let layout = bsp.generate_layout(8, 1920, 1080, 1, "eDP-1").unwrap();
+
+    // The vertical splits inside the first one are pulled halfway back to 0.5
+    assert_eq!(layout.views.len(), 8);
+    let cells: Vec<_> = layout
+        .views
+        .iter()
+        .map(|v| (v.x, v.y, v.width, v.height))
+        .collect();
+    assert_eq!(
+        cells,
+        vec![
+            (0, 0, 900, 540),
+            (900, 0, 540, 540),
+            (0, 540, 900, 540),
+            (900, 540, 540, 540),
+            (1440, 0, 300, 540),
+            (1740, 0, 180, 540),
+            (1440, 540, 300, 540),
+            (1740, 540, 180, 540),
+        ]
+    );
+}
+
+#[test]
+fn test_generate_layout_split_px() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_inner_gaps(0);
+    bsp.set_all_outer_gaps(0);
+    bsp.vsplit_px = 1200;
+    let layout = bsp.generate_layout(4, 1366, 768, 1, "eDP-1").unwrap();
+
+    // Only the first vertical split is in pixels, the horizontal splits keep their percentage
+    assert_eq!(layout.views.len(), 4);
+    let cells: Vec<_> = layout
+        .views
+        .iter()
+        .map(|v| (v.x, v.y, v.width, v.height))
+        .collect();
+    assert_eq!(
+        cells,
+        vec![
+            (0, 0, 1200, 384),
+            (0, 384, 1200, 384),
+            (1200, 0, 166, 384),
+            (1200, 384, 166, 384),
+        ]
+    );
+
+    // Clamped to the output
+    bsp.vsplit_px = 5000;
+    let layout = bsp.generate_layout(2, 1366, 768, 1, "eDP-1").unwrap();
+    let cells: Vec<_> = layout.views.iter().map(|v| (v.x, v.width)).collect();
+    assert_eq!(cells, vec![(0, 1365), (1365, 1)]);
+
+    // Only the bsp layout uses pixels
+    bsp.mode = LayoutMode::Dwindle;
+    let layout = bsp.generate_layout(2, 1366, 768, 1, "eDP-1").unwrap();
+    let cells: Vec<_> = layout.views.iter().map(|v| (v.x, v.width)).collect();
+    assert_eq!(cells, vec![(0, 683), (683, 683)]);
+}
+
+#[test]
+fn test_generate_layout_smart_gaps() {
+    let mut bsp = BSPLayout::new();
+    bsp.smart_gaps = SmartGaps::Single;
+    let layout = bsp.generate_layout(1, 1920, 1080, 1, "eDP-1").unwrap();
+    let first_view = layout.views.first().unwrap();
+    assert_eq!(
+        (
+            first_view.x,
+            first_view.y,
+            first_view.width,
+            first_view.height
+        ),
+        (0, 0, 1920, 1080)
+    );
+
+    // The gaps come back with a second view
+    let layout = bsp.generate_layout(2, 1920, 1080, 1, "eDP-1").unwrap();
+    let cells: Vec<_> = layout
+        .views
+        .iter()
+        .map(|v| (v.x, v.y, v.width, v.height))
+        .collect();
+    assert_eq!(cells, vec![(10, 10, 945, 1060), (965, 10, 945, 1060)]);
+
+    bsp.mode = LayoutMode::Monocle;
+    let layout = bsp.generate_layout(2, 1920, 1080, 1, "eDP-1").unwrap();
+    let first_view = layout.views.first().unwrap();
+    assert_eq!((first_view.x, first_view.width), (10, 1900));
+
+    // Monocle only shows one view, so it loses its outer gaps as well as its inner gaps
+    bsp.smart_gaps = SmartGaps::Stacked;
+    let layout = bsp.generate_layout(2, 1920, 1080, 1, "eDP-1").unwrap();
+    let cells: Vec<_> = layout
+        .views
+        .iter()
+        .map(|v| (v.x, v.y, v.width, v.height))
+        .collect();
+    assert_eq!(cells, vec![(0, 0, 1920, 1080), (0, 0, 1920, 1080)]);
+
+    // Other stacked views lose their inner gaps, but keep the outer gaps
+    bsp.mode = LayoutMode::Columns;
+    let layout = bsp.generate_layout(2, 1920, 1080, 1, "eDP-1").unwrap();
+    let cells: Vec<_> = layout
+        .views
+        .iter()
+        .map(|v| (v.x, v.y, v.width, v.height))
+        .collect();
+    assert_eq!(cells, vec![(10, 10, 950, 1060), (960, 10, 950, 1060)]);
+
+    let layout = bsp.generate_layout(1, 1920, 1080, 1, "eDP-1").unwrap();
+    let first_view = layout.views.first().unwrap();
+    assert_eq!((first_view.x, first_view.width), (0, 1920));
+
+    // The bsp layout divides the screen, so it keeps its inner gaps
+    bsp.mode = LayoutMode::Bsp;
+    let layout = bsp.generate_layout(2, 1920, 1080, 1, "eDP-1").unwrap();
+    let cells: Vec<_> = layout
+        .views
+        .iter()
+        .map(|v| (v.x, v.y, v.width, v.height))
+        .collect();
+    assert_eq!(cells, vec![(10, 10, 945, 1060), (965, 10, 945, 1060)]);
+}
+
+#[test]
+fn test_generate_layout_percent_gaps() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_outer_gaps(Gap::Percent(1.0));
+    bsp.set_inner_gaps(Gap::Percent(0.5));
+    let layout = bsp.generate_layout(2, 2000, 1000, 1, "eDP-1").unwrap();
+
+    // Left and right gaps follow the width, top and bottom gaps follow the height
+    let cells: Vec<_> = layout
+        .views
+        .iter()
+        .map(|v| (v.x, v.y, v.width, v.height))
+        .collect();
+    assert_eq!(cells, vec![(20, 10, 970, 980), (1010, 10, 970, 980)]);
+
+    let layout = bsp.generate_layout(1, 1000, 500, 1, "eDP-1").unwrap();
+    let first_view = layout.views.first().unwrap();
+    assert_eq!(
+        (
+            first_view.x,
+            first_view.y,
+            first_view.width,
+            first_view.height
+        ),
+        (10, 5, 980, 490)
+    );
+}
+
+#[test]
+fn test_generate_layout_gap_scale() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_outer_gaps(20);
+    bsp.set_all_inner_gaps(8);
+    bsp.gap_scale = "1.0,0.5@3".parse().unwrap();
+
+    let layout = bsp.generate_layout(1, 1920, 1080, 1, "eDP-1").unwrap();
+    let first_view = layout.views.first().unwrap();
+    assert_eq!(
+        (
+            first_view.x,
+            first_view.y,
+            first_view.width,
+            first_view.height
+        ),
+        (20, 20, 1880, 1040)
+    );
+
+    // Halfway between 1.0 and 0.5 the gaps are 3/4 of their size
+    let layout = bsp.generate_layout(2, 1920, 1080, 1, "eDP-1").unwrap();
+    let cells: Vec<_> = layout
+        .views
+        .iter()
+        .map(|v| (v.x, v.y, v.width, v.height))
+        .collect();
+    assert_eq!(cells, vec![(15, 15, 939, 1050), (966, 15, 939, 1050)]);
+
+    // From 3 views on the gaps are halved
+    let layout = bsp.generate_layout(4, 1920, 1080, 1, "eDP-1").unwrap();
+    let first_view = layout.views.first().unwrap();
+    assert_eq!(
+        (
+            first_view.x,
+            first_view.y,
+            first_view.width,
+            first_view.height
+        ),
+        (10, 10, 946, 526)
+    );
+}
+
+#[test]
+fn test_generate_layout_no_views() {
+    let mut bsp = BSPLayout::new();
+    for mode in LayoutMode::value_variants() {
+        bsp.mode = *mode;
+        let layout = bsp.generate_layout(0, 1920, 1080, 1, "eDP-1").unwrap();
+        assert!(layout.views.is_empty());
+    }
+
+    let layout = bsp.generate_layout(0, 0, 0, 1, "eDP-1").unwrap();
+    assert!(layout.views.is_empty());
+}
+
+#[test]
+fn test_generate_layout_gaps_larger_than_output() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_outer_gaps(1000);
+    let layout = bsp.generate_layout(1, 1920, 1080, 1, "eDP-1").unwrap();
+
+    // The outer gaps shrink in proportion to each other until 1 pixel is left
+    let first_view = layout.views.first().unwrap();
+    assert_eq!(
+        (
+            first_view.x,
+            first_view.y,
+            first_view.width,
+            first_view.height
+        ),
+        (959, 539, 1, 1)
+    );
+
+    bsp.set_all_outer_gaps(0);
+    bsp.og_left = Gap::Px(3000);
+    bsp.og_top = Gap::Percent(100.0);
+    bsp.og_bottom = Gap::Percent(100.0);
+    let layout = bsp.generate_layout(1, 1920, 1080, 1, "eDP-1").unwrap();
+    let first_view = layout.views.first().unwrap();
+    assert_eq!(
+        (
+            first_view.x,
+            first_view.y,
+            first_view.width,
+            first_view.height
+        ),
+        (1919, 539, 1, 1)
+    );
+
+    // Gaps that fit are left alone
+    bsp.set_all_outer_gaps(959);
+    let layout = bsp.generate_layout(1, 1920, 1080, 1, "eDP-1").unwrap();
+    let first_view = layout.views.first().unwrap();
+    assert_eq!((first_view.x, first_view.width), (959, 2));
+}
+
+#[test]
+fn test_generate_layout_degenerate_sizes() {
+    let gaps = [Gap::Px(0), Gap::Px(50), Gap::Px(3000), Gap::Percent(100.0)];
+    let mut bsp = BSPLayout::new();
+    bsp.main_count = 2;
+    bsp.vsplit_perc = 0.9;
+    bsp.hsplit_perc = 0.1;
+    for (mode, stack_sizing) in LayoutMode::value_variants()
+        .iter()
+        .flat_map(|m| [(*m, StackSizing::Equal), (*m, StackSizing::Geometric)])
+    {
+        bsp.mode = mode;
+        bsp.stack_sizing = stack_sizing;
+        for (outer_gap, inner_gap, reversed) in gaps
+            .iter()
+            .flat_map(|o| gaps.iter().map(move |i| (*o, *i)))
+            .flat_map(|(o, i)| [(o, i, false), (o, i, true)])
+        {
+            bsp.set_outer_gaps(outer_gap);
+            bsp.set_inner_gaps(inner_gap);
+            // Gaps that are only on one side must not push the views off the other side
+            bsp.og_right = Gap::Px(0);
+            bsp.ig_top = Gap::Px(0);
+            bsp.reversed = reversed;
+            for (width, height) in [(0, 0), (1, 1), (3, 2), (1920, 0), (1, 1080), (1920, 1080)] {
+                for view_count in 1..=12 {
+                    let layout = bsp
+                        .generate_layout(view_count, width, height, 1, "eDP-1")
+                        .unwrap();
+                    assert_eq!(layout.views.len(), view_count as usize);
+
+                    // An output without any pixels still gets views of 1 pixel
+                    let (width, height) = (width.max(1) as i32, height.max(1) as i32);
+                    for v in &layout.views {
+                        assert!(v.width > 0 && v.height > 0);
+                        assert!(v.x >= 0 && v.x + v.width as i32 <= width, "{:?}", v);
+                        assert!(v.y >= 0 && v.y + v.height as i32 <= height, "{:?}", v);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_generate_layout_huge_options() {
+    let mut bsp = BSPLayout::new();
+    bsp.main_count = u32::MAX;
+    bsp.grid_cols = u32::MAX;
+    bsp.grid_rows = u32::MAX;
+    bsp.hsplit_px = u32::MAX;
+    bsp.vsplit_px = u32::MAX;
+    for (mode, fill) in LayoutMode::value_variants()
+        .iter()
+        .flat_map(|m| [(*m, GridFill::Stretch), (*m, GridFill::Center)])
+    {
+        bsp.mode = mode;
+        bsp.grid_fill = fill;
+        for view_count in 0..=4 {
+            // Nothing is allocated for each of the huge number of cells, so there is no crash
+            let layout = bsp
+                .generate_layout(view_count, 1920, 1080, 1, "eDP-1")
+                .unwrap();
+            assert_eq!(layout.views.len(), view_count as usize);
+            for v in &layout.views {
+                assert!(v.width > 0 && v.height > 0);
+                assert!(v.x >= 0 && v.x + v.width as i32 <= 1920, "{:?}", v);
+                assert!(v.y >= 0 && v.y + v.height as i32 <= 1080, "{:?}", v);
+            }
+        }
+    }
+}
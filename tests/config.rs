@@ -0,0 +1,304 @@
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use river_bsp_layout::config::{Config, ConfigSource, LayoutOptions};
+use river_bsp_layout::gap::Gap;
+use river_bsp_layout::state::StateFile;
+use river_bsp_layout::BSPLayout;
+use river_layout_toolkit::Layout;
+
+/// Write `contents` to a config file in the temp directory that is unique to `name`
+fn write_config(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "river-bsp-layout-{}-{}.toml",
+        name,
+        std::process::id()
+    ));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn test_parse_config() {
+    let config = Config::parse(
+        r#"
+        inner-gap = 5
+        og-top = 0
+        split-perc = 0.6
+
+        [output.DP-2]
+        outer-gap = 20
+
+        [tags.256]
+        start-hsplit = true
+        reverse = true
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.defaults.inner_gap, Some(Gap::Px(5)));
+    assert_eq!(config.defaults.og_top, Some(Gap::Px(0)));
+    assert_eq!(config.defaults.split_perc, Some(0.6));
+    assert_eq!(config.defaults.outer_gap, None);
+    assert_eq!(
+        config.output.get("DP-2").unwrap().outer_gap,
+        Some(Gap::Px(20))
+    );
+    assert_eq!(config.tags.get(&256).unwrap().start_hsplit, Some(true));
+    assert_eq!(config.tags.get(&256).unwrap().reverse, Some(true));
+}
+
+#[test]
+fn test_parse_invalid_config() {
+    assert!(Config::parse("inner-gap = \"five\"").is_err());
+    assert!(Config::parse("split-perc = 1.5").is_err());
+    assert!(Config::parse("[output.DP-2]\nvsplit-perc = 0.0").is_err());
+    assert!(Config::parse("[output.DP-2]\nnot-an-option = 1").is_err());
+    assert!(Config::parse("[tags.first]\nreverse = true").is_err());
+    assert!(Config::parse("inner-gapp = 5").is_err());
+    assert!(Config::parse("layout-cycle = []").is_err());
+    assert!(Config::parse("[split-tree.secondary]\nratio = 1.5").is_err());
+    assert!(Config::parse("layout-cycle = [\"tabbed\"]").is_err());
+    assert!(Config::parse("deeper-vsplit-perc = [0.5, 1.0]").is_err());
+    assert!(Config::parse("split-decay = 1.5").is_err());
+}
+
+#[test]
+fn test_apply_config() {
+    let config = Config::parse(
+        r#"
+        inner-gap = 5
+        outer-gap = 10
+        hsplit-perc = 0.4
+
+        [output.DP-2]
+        outer-gap = 20
+
+        [tags.2]
+        og-left = 0
+        "#,
+    )
+    .unwrap();
+
+    let mut bsp = BSPLayout::new();
+    config.apply(&mut bsp);
+    assert_eq!(
+        (bsp.ig_top, bsp.ig_left, bsp.ig_right, bsp.ig_bottom),
+        (Gap::Px(5), Gap::Px(5), Gap::Px(5), Gap::Px(5))
+    );
+    assert_eq!(
+        (bsp.og_top, bsp.og_left, bsp.og_right, bsp.og_bottom),
+        (Gap::Px(10), Gap::Px(10), Gap::Px(10), Gap::Px(10))
+    );
+    assert_eq!((bsp.hsplit_perc, bsp.vsplit_perc), (0.4, 0.5));
+
+    // Output options are applied before tag options
+    bsp.user_cmd("--reverse".to_string(), Some(2), "DP-2")
+        .unwrap();
+    let state = bsp.state("DP-2", 2).unwrap();
+    assert_eq!(
+        (state.og_top, state.og_left, state.og_right, state.og_bottom),
+        (Gap::Px(20), Gap::Px(0), Gap::Px(20), Gap::Px(20))
+    );
+
+    let layout = bsp.generate_layout(1, 1920, 1080, 1, "DP-2").unwrap();
+    let first_view = layout.views.first().unwrap();
+    assert_eq!(
+        (
+            first_view.x,
+            first_view.y,
+            first_view.width,
+            first_view.height
+        ),
+        (20, 20, 1880, 1040)
+    );
+
+    let layout = bsp.generate_layout(1, 1920, 1080, 1, "eDP-1").unwrap();
+    let first_view = layout.views.first().unwrap();
+    assert_eq!(
+        (
+            first_view.x,
+            first_view.y,
+            first_view.width,
+            first_view.height
+        ),
+        (10, 10, 1900, 1060)
+    );
+}
+
+#[test]
+fn test_build_from_source() {
+    let path = write_config("build", "outer-gap = 10\nsplit-perc = 0.6");
+    let overrides = LayoutOptions {
+        og_top: Some(Gap::Px(0)),
+        vsplit_perc: Some(0.4),
+        ..Default::default()
+    };
+    let source = ConfigSource::new(path.clone(), true, LayoutOptions::default(), overrides);
+    let bsp = source.build().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        (bsp.og_top, bsp.og_left, bsp.og_right, bsp.og_bottom),
+        (Gap::Px(0), Gap::Px(10), Gap::Px(10), Gap::Px(10))
+    );
+    assert_eq!((bsp.hsplit_perc, bsp.vsplit_perc), (0.6, 0.4));
+
+    let mut missing = ConfigSource::new(
+        path.clone(),
+        false,
+        LayoutOptions::default(),
+        LayoutOptions::default(),
+    );
+    assert!(missing.build().is_ok());
+    missing.required = true;
+    assert!(missing.build().is_err());
+}
+
+#[test]
+fn test_overrides_beat_sections() {
+    let path = write_config(
+        "sections",
+        "[output.eDP-1]\nouter-gap = 5\ninner-gap = 2\n\n[tags.2]\nouter-gap = 7",
+    );
+    let overrides = LayoutOptions {
+        outer_gap: Some(Gap::Px(20)),
+        inner_gap: Some(Gap::Px(3)),
+        ..Default::default()
+    };
+    let source = ConfigSource::new(path.clone(), true, LayoutOptions::default(), overrides);
+    let mut bsp = source.build().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    // The command line beats the file, but options only the file sets still apply
+    bsp.generate_layout(1, 1920, 1080, 1, "eDP-1").unwrap();
+    let state = bsp.state("eDP-1", 1).unwrap();
+    assert_eq!((state.og_left, state.ig_left), (Gap::Px(20), Gap::Px(3)));
+    bsp.generate_layout(1, 1920, 1080, 2, "DP-2").unwrap();
+    assert_eq!(bsp.state("DP-2", 2).unwrap().og_left, Gap::Px(20));
+
+    // Commands sent to the defaults are not undone by the command line options
+    bsp.user_cmd("--inner-gap 4".to_string(), None, "DP-3")
+        .unwrap();
+    bsp.generate_layout(1, 1920, 1080, 2, "DP-3").unwrap();
+    let state = bsp.state("DP-3", 2).unwrap();
+    assert_eq!((state.og_left, state.ig_left), (Gap::Px(20), Gap::Px(4)));
+}
+
+#[test]
+fn test_group_overrides_beat_sections() {
+    let path = write_config(
+        "group_sections",
+        "ig-left = 2
+vsplit-perc = 0.75
+
+[output.o]
+ig-left = 2
+vsplit-perc = 0.75
+
+\
+         [tags.2]
+inner-gap = 6
+split-perc = 0.25",
+    );
+    let overrides = LayoutOptions {
+        inner_gap: Some(Gap::Px(4)),
+        split_perc: Some(0.625),
+        ig_top: Some(Gap::Px(8)),
+        hsplit_perc: Some(0.375),
+        ..Default::default()
+    };
+    let source = ConfigSource::new(path.clone(), true, LayoutOptions::default(), overrides);
+    let mut bsp = source.build().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!((bsp.ig_left, bsp.vsplit_perc), (Gap::Px(4), 0.625));
+
+    // A flag for every side beats an option for one side
+    bsp.generate_layout(1, 1920, 1080, 1, "o").unwrap();
+    let state = bsp.state("o", 1).unwrap();
+    assert_eq!((state.ig_left, state.vsplit_perc), (Gap::Px(4), 0.625));
+    assert_eq!((state.ig_top, state.hsplit_perc), (Gap::Px(8), 0.375));
+
+    // And a flag for one side beats an option for every side
+    bsp.generate_layout(1, 1920, 1080, 2, "DP-2").unwrap();
+    let state = bsp.state("DP-2", 2).unwrap();
+    assert_eq!((state.ig_left, state.ig_top), (Gap::Px(4), Gap::Px(8)));
+    assert_eq!((state.vsplit_perc, state.hsplit_perc), (0.625, 0.375));
+}
+
+#[test]
+fn test_reload_config() {
+    let path = write_config("reload", "outer-gap = 10");
+    let source = ConfigSource::new(
+        path.clone(),
+        true,
+        LayoutOptions::default(),
+        LayoutOptions::default(),
+    );
+    let state_path = path.with_extension("state");
+    let mut bsp = source.build().unwrap();
+    bsp.persist_to(StateFile::new(state_path.clone()));
+    bsp.user_cmd("--outer-gap 20".to_string(), Some(1), "eDP-1")
+        .unwrap();
+
+    std::fs::write(&path, "outer-gap = 5\nmain-count = 3").unwrap();
+    bsp.user_cmd("--reload".to_string(), Some(1), "eDP-1")
+        .unwrap();
+    assert_eq!(bsp.og_top, Gap::Px(5));
+
+    // The states keep the changes made by user commands, and follow the file otherwise
+    let state = bsp.state("eDP-1", 1).unwrap();
+    assert_eq!((state.og_top, state.main_count), (Gap::Px(20), 3));
+
+    // The state file still has the changes after the reload
+    let mut restored = source.build().unwrap();
+    StateFile::new(state_path.clone())
+        .restore(&mut restored)
+        .unwrap();
+    std::fs::remove_file(&state_path).unwrap();
+    assert_eq!(restored.state("eDP-1", 1).unwrap().og_top, Gap::Px(20));
+
+    // A broken file keeps the current state
+    std::fs::write(&path, "outer-gap = ").unwrap();
+    assert!(bsp
+        .user_cmd("--reload".to_string(), Some(1), "eDP-1")
+        .is_err());
+    assert_eq!(bsp.og_top, Gap::Px(5));
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(BSPLayout::new()
+        .user_cmd("--reload".to_string(), None, "")
+        .is_err());
+}
+
+#[test]
+fn test_watch_config() {
+    let path = write_config("watch", "outer-gap = 10");
+    let source = ConfigSource::new(
+        path.clone(),
+        true,
+        LayoutOptions::default(),
+        LayoutOptions::default(),
+    );
+    let (sender, receiver) = mpsc::channel();
+    source
+        .watch(move || {
+            let _ = sender.send(());
+        })
+        .unwrap();
+
+    // Other files in the same directory are left alone
+    let other = write_config("watch-other", "outer-gap = 0");
+    std::fs::remove_file(&other).unwrap();
+    assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+
+    std::fs::write(&path, "outer-gap = 0").unwrap();
+    assert!(receiver.recv_timeout(Duration::from_secs(5)).is_ok());
+
+    // Saving by replacing the file is noticed as well
+    let replacement = write_config("watch-replacement", "outer-gap = 5");
+    std::fs::rename(&replacement, &path).unwrap();
+    assert!(receiver.recv_timeout(Duration::from_secs(5)).is_ok());
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,84 @@
+use river_bsp_layout::config::Config;
+use river_bsp_layout::gap::{Gap, GapScale};
+
+#[test]
+fn test_parse_gap() {
+    assert_eq!("10".parse::<Gap>().unwrap(), Gap::Px(10));
+    assert_eq!("10px".parse::<Gap>().unwrap(), Gap::Px(10));
+    assert_eq!("1%".parse::<Gap>().unwrap(), Gap::Percent(1.0));
+    assert_eq!(" 2.5 % ".parse::<Gap>().unwrap(), Gap::Percent(2.5));
+    assert!("ten".parse::<Gap>().is_err());
+    assert!("-1".parse::<Gap>().is_err());
+    assert!("150%".parse::<Gap>().is_err());
+    assert_eq!(Gap::Percent(2.5).to_string(), "2.5%".to_string());
+}
+
+#[test]
+fn test_gap_pixels() {
+    assert_eq!(Gap::Px(10).pixels(1920), 10);
+    assert_eq!(Gap::Percent(1.0).pixels(1920), 19);
+    assert_eq!(Gap::Percent(1.0).pixels(1080), 11);
+}
+
+#[test]
+fn test_gap_change() {
+    assert_eq!(Gap::Px(5).change(Gap::Px(2), true), Ok(Gap::Px(7)));
+    assert_eq!(Gap::Px(5).change(Gap::Px(8), false), Ok(Gap::Px(0)));
+    assert_eq!(
+        Gap::Percent(1.0).change(Gap::Percent(0.5), true),
+        Ok(Gap::Percent(1.5))
+    );
+    assert_eq!(
+        Gap::Percent(99.5).change(Gap::Percent(1.0), true),
+        Ok(Gap::Percent(100.0))
+    );
+    assert_eq!(
+        Gap::Percent(1.0).change(Gap::Percent(2.0), false),
+        Ok(Gap::Percent(0.0))
+    );
+    assert!(Gap::Percent(1.0).change(Gap::Px(2), true).is_err());
+    assert!(Gap::Px(1).change(Gap::Percent(2.0), false).is_err());
+}
+
+#[test]
+fn test_gap_in_config() {
+    let config = Config::parse("inner-gap = 5\nouter-gap = \"1%\"\nog-top = \"0px\"").unwrap();
+    assert_eq!(config.defaults.inner_gap, Some(Gap::Px(5)));
+    assert_eq!(config.defaults.outer_gap, Some(Gap::Percent(1.0)));
+    assert_eq!(config.defaults.og_top, Some(Gap::Px(0)));
+    assert!(Config::parse("outer-gap = \"1 em\"").is_err());
+}
+
+#[test]
+fn test_gap_scale() {
+    let scale: GapScale = "1.0,0.75,0.5@6".parse().unwrap();
+    let factors: Vec<f32> = (1..=8).map(|n| scale.factor(n)).collect();
+    assert_eq!(
+        factors,
+        vec![1.0, 0.75, 0.6875, 0.625, 0.5625, 0.5, 0.5, 0.5]
+    );
+    assert_eq!(scale.to_string(), "1,0.75,0.5@6".to_string());
+
+    let scale: GapScale = "0.5@3, 0.25".parse().unwrap();
+    assert_eq!(
+        (scale.factor(1), scale.factor(3), scale.factor(9)),
+        (0.5, 0.5, 0.25)
+    );
+
+    assert_eq!(GapScale::default().factor(5), 1.0);
+    assert_eq!("".parse::<GapScale>().unwrap(), GapScale::default());
+    assert!("1.0,0.5@1".parse::<GapScale>().is_err());
+    assert!("1.0,-0.5".parse::<GapScale>().is_err());
+    assert!("1.0,half".parse::<GapScale>().is_err());
+    assert!("1.0,0.5@0".parse::<GapScale>().is_err());
+}
+
+#[test]
+fn test_gap_scale_in_config() {
+    let config = Config::parse("gap-scale = \"1.0,0.5@3\"").unwrap();
+    assert_eq!(
+        config.defaults.gap_scale,
+        Some("1.0,0.5@3".parse().unwrap())
+    );
+    assert!(Config::parse("gap-scale = \"1.0,0.5@1\"").is_err());
+}
@@ -0,0 +1,163 @@
+use std::path::PathBuf;
+
+use river_bsp_layout::config::{ConfigSource, LayoutOptions};
+use river_bsp_layout::gap::Gap;
+use river_bsp_layout::state::StateFile;
+use river_bsp_layout::tree::Side;
+use river_bsp_layout::{BSPLayout, LayoutMode};
+use river_layout_toolkit::Layout;
+
+/// Get a path in the temp directory for a state file that is unique to `name`
+fn state_path(name: &str) -> PathBuf {
+    std::env::temp_dir()
+        .join(format!("river-bsp-layout-{}-{}", name, std::process::id()))
+        .join("state.toml")
+}
+
+#[test]
+fn test_persist_state() {
+    let path = state_path("persist");
+    let mut bsp = BSPLayout::new();
+    bsp.persist_to(StateFile::new(path.clone()));
+    bsp.user_cmd("--outer-gap 3".to_string(), None, "eDP-1")
+        .unwrap();
+    bsp.user_cmd("--inc-hsplit 0.1 --reverse".to_string(), Some(1), "eDP-1")
+        .unwrap();
+    bsp.user_cmd("--start-hsplit".to_string(), Some(4), "DP-2")
+        .unwrap();
+    bsp.user_cmd(
+        "--node-path /s --node-ratio 0.25".to_string(),
+        Some(4),
+        "DP-2",
+    )
+    .unwrap();
+    assert!(path.exists());
+
+    let mut restored = BSPLayout::new();
+    StateFile::new(path.clone()).restore(&mut restored).unwrap();
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+    let defaults = restored.output_state("eDP-1").unwrap();
+    assert_eq!(
+        (
+            defaults.og_top,
+            defaults.og_left,
+            defaults.og_right,
+            defaults.og_bottom
+        ),
+        (Gap::Px(3), Gap::Px(3), Gap::Px(3), Gap::Px(3))
+    );
+    assert_eq!(restored.og_top, Gap::Px(10));
+
+    let laptop = restored.state("eDP-1", 1).unwrap();
+    assert_eq!(laptop.hsplit_perc, 0.6);
+    assert!(laptop.reversed);
+    assert!(!laptop.start_hsplit);
+    assert_eq!(laptop.og_top, Gap::Px(3));
+    assert_eq!(laptop.split_tree, Default::default());
+
+    let external = restored.state("DP-2", 4).unwrap();
+    assert_eq!(external.hsplit_perc, 0.5);
+    assert!(!external.reversed);
+    assert!(external.start_hsplit);
+    assert_eq!(external.og_top, Gap::Px(10));
+    assert_eq!(
+        external.split_tree.get(&[Side::Secondary]).unwrap().ratio,
+        Some(0.25)
+    );
+}
+
+#[test]
+fn test_restore_missing_state() {
+    let mut bsp = BSPLayout::new();
+    StateFile::new(state_path("missing"))
+        .restore(&mut bsp)
+        .unwrap();
+    assert_eq!(bsp.og_top, Gap::Px(10));
+    assert!(bsp.state("eDP-1", 1).is_none());
+}
+
+#[test]
+fn test_restore_invalid_state() {
+    let path = state_path("invalid");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, "states = 5").unwrap();
+
+    let mut bsp = BSPLayout::new();
+    assert!(StateFile::new(path.clone()).restore(&mut bsp).is_err());
+
+    std::fs::write(
+        &path,
+        "[[states]]\noutput = \"eDP-1\"\ntags = 1\nog-top = 0\nvsplit-perc = 1.5",
+    )
+    .unwrap();
+    assert!(StateFile::new(path.clone()).restore(&mut bsp).is_err());
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    assert!(bsp.state("eDP-1", 1).is_none());
+}
+
+#[test]
+fn test_persist_toggles() {
+    let path = state_path("toggles");
+    let mut bsp = BSPLayout::new();
+    bsp.persist_to(StateFile::new(path.clone()));
+    bsp.user_cmd("--layout grid".to_string(), None, "eDP-1")
+        .unwrap();
+    bsp.user_cmd("--toggle-gaps --toggle-monocle".to_string(), None, "eDP-1")
+        .unwrap();
+    bsp.user_cmd("--toggle-gaps".to_string(), Some(1), "eDP-1")
+        .unwrap();
+
+    let mut restored = BSPLayout::new();
+    StateFile::new(path.clone()).restore(&mut restored).unwrap();
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+    // The toggles still go back to what was used before the restart
+    restored
+        .user_cmd("--toggle-gaps --toggle-monocle".to_string(), None, "eDP-1")
+        .unwrap();
+    let defaults = restored.output_state("eDP-1").unwrap();
+    assert!(defaults.gaps_enabled);
+    assert_eq!(defaults.mode, LayoutMode::Grid);
+
+    // The state toggled its gaps back on, so toggling them again turns them off
+    assert!(!restored.state("eDP-1", 1).unwrap().gaps_enabled);
+}
+
+#[test]
+fn test_persist_changes_only() {
+    let path = state_path("changes");
+    let config = path.with_file_name("config.toml");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&config, "outer-gap = 20\nhsplit-perc = 0.6").unwrap();
+    let overrides = LayoutOptions {
+        main_count: Some(2),
+        ..Default::default()
+    };
+    let source = ConfigSource::new(config.clone(), true, LayoutOptions::default(), overrides);
+
+    let mut bsp = source.build().unwrap();
+    bsp.persist_to(StateFile::new(path.clone()));
+    bsp.user_cmd("--inc-hsplit 0.1".to_string(), Some(1), "eDP-1")
+        .unwrap();
+    bsp.user_cmd("--inner-gap 2".to_string(), None, "DP-2")
+        .unwrap();
+
+    // Options that were not changed with a user command follow the config file of the next run
+    std::fs::write(&config, "outer-gap = 30\nhsplit-perc = 0.4\nmain-count = 3").unwrap();
+    let mut restored = source.build().unwrap();
+    StateFile::new(path.clone()).restore(&mut restored).unwrap();
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+    let laptop = restored.state("eDP-1", 1).unwrap();
+    assert!((laptop.hsplit_perc - 0.7).abs() < 1e-6);
+    assert_eq!(laptop.og_left, Gap::Px(30));
+    assert_eq!(laptop.main_count, 2);
+
+    let external = restored.output_state("DP-2").unwrap();
+    assert_eq!(
+        (external.ig_left, external.og_left),
+        (Gap::Px(2), Gap::Px(30))
+    );
+    assert_eq!(external.hsplit_perc, 0.4);
+}
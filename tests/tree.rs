@@ -0,0 +1,49 @@
+use river_bsp_layout::tree::{self, Side, SplitNode};
+
+#[test]
+fn test_parse_path() {
+    assert_eq!(tree::parse_path("/").unwrap(), vec![]);
+    assert_eq!(
+        tree::parse_path("/s/p").unwrap(),
+        vec![Side::Secondary, Side::Primary]
+    );
+    assert!(tree::parse_path("s/p").is_err());
+    assert!(tree::parse_path("/s/x").is_err());
+    assert_eq!(
+        tree::format_path(&[Side::Secondary, Side::Primary]),
+        "/s/p".to_string()
+    );
+}
+
+#[test]
+fn test_path_of_view() {
+    // With five views, the root holds two views on the primary side and three on the secondary
+    assert_eq!(tree::path_of_view(5, 0), Some(vec![Side::Primary]));
+    assert_eq!(tree::path_of_view(5, 2), Some(vec![Side::Secondary]));
+    assert_eq!(
+        tree::path_of_view(5, 4),
+        Some(vec![Side::Secondary, Side::Secondary])
+    );
+    assert_eq!(tree::path_of_view(2, 1), Some(vec![]));
+    assert_eq!(tree::path_of_view(1, 0), None);
+    assert_eq!(tree::path_of_view(5, 5), None);
+
+    assert_eq!(tree::views_at(5, &[Side::Secondary]), Some(3));
+    assert_eq!(tree::views_at(5, &[Side::Secondary, Side::Primary]), None);
+}
+
+#[test]
+fn test_split_node() {
+    let mut node = SplitNode::default();
+    node.get_mut(&[Side::Secondary, Side::Primary]).ratio = Some(0.3);
+    assert_eq!(
+        node.get(&[Side::Secondary, Side::Primary]).unwrap().ratio,
+        Some(0.3)
+    );
+    assert_eq!(node.get(&[Side::Secondary]).unwrap().ratio, None);
+    assert!(node.get(&[Side::Primary]).is_none());
+    assert!(node.validate().is_ok());
+
+    node.get_mut(&[Side::Primary]).ratio = Some(1.0);
+    assert!(node.validate().is_err());
+}
@@ -1,5 +1,14 @@
-use river_bsp_layout::BSPLayout;
-use river_layout_toolkit::Layout;
+use river_bsp_layout::gap::Gap;
+use river_bsp_layout::grid::GridFill;
+use river_bsp_layout::master_stack::MainLocation;
+use river_bsp_layout::tree::Side;
+use river_bsp_layout::{BSPLayout, LayoutMode, SmartGaps, SplitDirection, StackSizing};
+use river_layout_toolkit::{GeneratedLayout, Layout};
+
+/// Get the defaults of the output `""`, which the commands without tags in these tests change
+fn defaults(bsp: &BSPLayout) -> &BSPLayout {
+    bsp.output_state("").unwrap()
+}
 
 #[test]
 fn test_handle_outer_gaps() {
@@ -7,33 +16,58 @@ fn test_handle_outer_gaps() {
     bsp.set_all_outer_gaps(0);
     bsp.user_cmd("--outer-gap 5".to_string(), None, "").unwrap();
     assert_eq!(
-        (bsp.og_top, bsp.og_left, bsp.og_right, bsp.og_bottom),
-        (5, 5, 5, 5)
+        (
+            defaults(&bsp).og_top,
+            defaults(&bsp).og_left,
+            defaults(&bsp).og_right,
+            defaults(&bsp).og_bottom
+        ),
+        (Gap::Px(5), Gap::Px(5), Gap::Px(5), Gap::Px(5))
     );
 
     bsp.user_cmd("--og-top 10".to_string(), None, "").unwrap();
     assert_eq!(
-        (bsp.og_top, bsp.og_left, bsp.og_right, bsp.og_bottom),
-        (10, 5, 5, 5)
+        (
+            defaults(&bsp).og_top,
+            defaults(&bsp).og_left,
+            defaults(&bsp).og_right,
+            defaults(&bsp).og_bottom
+        ),
+        (Gap::Px(10), Gap::Px(5), Gap::Px(5), Gap::Px(5))
     );
 
     bsp.user_cmd("--og-left 10".to_string(), None, "").unwrap();
     assert_eq!(
-        (bsp.og_top, bsp.og_left, bsp.og_right, bsp.og_bottom),
-        (10, 10, 5, 5)
+        (
+            defaults(&bsp).og_top,
+            defaults(&bsp).og_left,
+            defaults(&bsp).og_right,
+            defaults(&bsp).og_bottom
+        ),
+        (Gap::Px(10), Gap::Px(10), Gap::Px(5), Gap::Px(5))
     );
 
     bsp.user_cmd("--og-right 10".to_string(), None, "").unwrap();
     assert_eq!(
-        (bsp.og_top, bsp.og_left, bsp.og_right, bsp.og_bottom),
-        (10, 10, 10, 5)
+        (
+            defaults(&bsp).og_top,
+            defaults(&bsp).og_left,
+            defaults(&bsp).og_right,
+            defaults(&bsp).og_bottom
+        ),
+        (Gap::Px(10), Gap::Px(10), Gap::Px(10), Gap::Px(5))
     );
 
     bsp.user_cmd("--og-bottom 10".to_string(), None, "")
         .unwrap();
     assert_eq!(
-        (bsp.og_top, bsp.og_left, bsp.og_right, bsp.og_bottom),
-        (10, 10, 10, 10)
+        (
+            defaults(&bsp).og_top,
+            defaults(&bsp).og_left,
+            defaults(&bsp).og_right,
+            defaults(&bsp).og_bottom
+        ),
+        (Gap::Px(10), Gap::Px(10), Gap::Px(10), Gap::Px(10))
     );
 
     bsp.user_cmd(
@@ -43,8 +77,13 @@ fn test_handle_outer_gaps() {
     )
     .unwrap();
     assert_eq!(
-        (bsp.og_top, bsp.og_left, bsp.og_right, bsp.og_bottom),
-        (0, 1, 2, 3)
+        (
+            defaults(&bsp).og_top,
+            defaults(&bsp).og_left,
+            defaults(&bsp).og_right,
+            defaults(&bsp).og_bottom
+        ),
+        (Gap::Px(0), Gap::Px(1), Gap::Px(2), Gap::Px(3))
     );
 }
 
@@ -52,35 +91,60 @@ fn test_handle_outer_gaps() {
 fn test_handle_inner_gaps() {
     let mut bsp = BSPLayout::new();
     bsp.user_cmd("--inner-gap 5".to_string(), None, "").unwrap();
-    bsp.set_all_inner_gaps(0);
+    bsp.user_cmd("--inner-gap 0".to_string(), None, "").unwrap();
     assert_eq!(
-        (bsp.ig_top, bsp.ig_left, bsp.ig_right, bsp.ig_bottom),
-        (0, 0, 0, 0)
+        (
+            defaults(&bsp).ig_top,
+            defaults(&bsp).ig_left,
+            defaults(&bsp).ig_right,
+            defaults(&bsp).ig_bottom
+        ),
+        (Gap::Px(0), Gap::Px(0), Gap::Px(0), Gap::Px(0))
     );
 
     bsp.user_cmd("--ig-top 10".to_string(), None, "").unwrap();
     assert_eq!(
-        (bsp.ig_top, bsp.ig_left, bsp.ig_right, bsp.ig_bottom),
-        (10, 0, 0, 0)
+        (
+            defaults(&bsp).ig_top,
+            defaults(&bsp).ig_left,
+            defaults(&bsp).ig_right,
+            defaults(&bsp).ig_bottom
+        ),
+        (Gap::Px(10), Gap::Px(0), Gap::Px(0), Gap::Px(0))
     );
 
     bsp.user_cmd("--ig-left 10".to_string(), None, "").unwrap();
     assert_eq!(
-        (bsp.ig_top, bsp.ig_left, bsp.ig_right, bsp.ig_bottom),
-        (10, 10, 0, 0)
+        (
+            defaults(&bsp).ig_top,
+            defaults(&bsp).ig_left,
+            defaults(&bsp).ig_right,
+            defaults(&bsp).ig_bottom
+        ),
+        (Gap::Px(10), Gap::Px(10), Gap::Px(0), Gap::Px(0))
     );
 
     bsp.user_cmd("--ig-right 10".to_string(), None, "").unwrap();
     assert_eq!(
-        (bsp.ig_top, bsp.ig_left, bsp.ig_right, bsp.ig_bottom),
-        (10, 10, 10, 0)
+        (
+            defaults(&bsp).ig_top,
+            defaults(&bsp).ig_left,
+            defaults(&bsp).ig_right,
+            defaults(&bsp).ig_bottom
+        ),
+        (Gap::Px(10), Gap::Px(10), Gap::Px(10), Gap::Px(0))
     );
 
     bsp.user_cmd("--ig-bottom 10".to_string(), None, "")
         .unwrap();
     assert_eq!(
-        (bsp.ig_top, bsp.ig_left, bsp.ig_right, bsp.ig_bottom),
-        (10, 10, 10, 10)
+        (
+            defaults(&bsp).ig_top,
+            defaults(&bsp).ig_left,
+            defaults(&bsp).ig_right,
+            defaults(&bsp).ig_bottom
+        ),
+        (Gap::Px(10), Gap::Px(10), Gap::Px(10), Gap::Px(10))
     );
 
     bsp.user_cmd(
@@ -90,9 +154,183 @@ fn test_handle_inner_gaps() {
     )
     .unwrap();
     assert_eq!(
-        (bsp.ig_top, bsp.ig_left, bsp.ig_right, bsp.ig_bottom),
-        (0, 1, 2, 3)
+        (
+            defaults(&bsp).ig_top,
+            defaults(&bsp).ig_left,
+            defaults(&bsp).ig_right,
+            defaults(&bsp).ig_bottom
+        ),
+        (Gap::Px(0), Gap::Px(1), Gap::Px(2), Gap::Px(3))
+    );
+}
+
+#[test]
+fn test_handle_gap_units() {
+    let mut bsp = BSPLayout::new();
+    bsp.user_cmd("--outer-gap 1% --ig-left 4px".to_string(), None, "")
+        .unwrap();
+    assert_eq!(
+        (
+            defaults(&bsp).og_top,
+            defaults(&bsp).og_left,
+            defaults(&bsp).og_right,
+            defaults(&bsp).og_bottom
+        ),
+        (
+            Gap::Percent(1.0),
+            Gap::Percent(1.0),
+            Gap::Percent(1.0),
+            Gap::Percent(1.0)
+        )
+    );
+    assert_eq!(
+        (
+            defaults(&bsp).ig_top,
+            defaults(&bsp).ig_left,
+            defaults(&bsp).ig_right,
+            defaults(&bsp).ig_bottom
+        ),
+        (Gap::Px(5), Gap::Px(4), Gap::Px(5), Gap::Px(5))
+    );
+
+    // Invalid gaps are reported without changing anything
+    bsp.user_cmd("--inner-gap 5em".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).ig_top, Gap::Px(5));
+}
+
+#[test]
+fn test_handle_ch_gaps() {
+    let mut bsp = BSPLayout::new();
+    bsp.user_cmd(
+        "--inc-inner-gap 3 --dec-outer-gap 4px".to_string(),
+        None,
+        "",
+    )
+    .unwrap();
+    assert_eq!(
+        (
+            defaults(&bsp).ig_left,
+            defaults(&bsp).ig_right,
+            defaults(&bsp).ig_bottom,
+            defaults(&bsp).ig_top
+        ),
+        (Gap::Px(8), Gap::Px(8), Gap::Px(8), Gap::Px(8))
+    );
+    assert_eq!(
+        (
+            defaults(&bsp).og_left,
+            defaults(&bsp).og_right,
+            defaults(&bsp).og_bottom,
+            defaults(&bsp).og_top
+        ),
+        (Gap::Px(6), Gap::Px(6), Gap::Px(6), Gap::Px(6))
+    );
+
+    bsp.user_cmd(
+        "--dec-outer-gap 10 --inc-inner-gap 1 --gap-side top".to_string(),
+        None,
+        "",
+    )
+    .unwrap();
+    assert_eq!(
+        (
+            defaults(&bsp).ig_left,
+            defaults(&bsp).ig_right,
+            defaults(&bsp).ig_bottom,
+            defaults(&bsp).ig_top
+        ),
+        (Gap::Px(8), Gap::Px(8), Gap::Px(8), Gap::Px(9))
+    );
+    assert_eq!(
+        (
+            defaults(&bsp).og_left,
+            defaults(&bsp).og_right,
+            defaults(&bsp).og_bottom,
+            defaults(&bsp).og_top
+        ),
+        (Gap::Px(6), Gap::Px(6), Gap::Px(6), Gap::Px(0))
+    );
+
+    // A percentage cannot change a gap in pixels, and no gap is changed
+    bsp.user_cmd("--og-left 1%".to_string(), None, "").unwrap();
+    assert!(bsp
+        .user_cmd("--inc-outer-gap 0.5%".to_string(), None, "")
+        .is_err());
+    assert_eq!(
+        (
+            defaults(&bsp).og_left,
+            defaults(&bsp).og_right,
+            defaults(&bsp).og_bottom,
+            defaults(&bsp).og_top
+        ),
+        (Gap::Percent(1.0), Gap::Px(6), Gap::Px(6), Gap::Px(0))
+    );
+    bsp.user_cmd("--inc-outer-gap 0.5% --gap-side left".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).og_left, Gap::Percent(1.5));
+}
+
+/// Get the position and size of every view of a layout
+fn geometry(layout: &GeneratedLayout) -> Vec<(i32, i32, u32, u32)> {
+    layout
+        .views
+        .iter()
+        .map(|v| (v.x, v.y, v.width, v.height))
+        .collect()
+}
+
+#[test]
+fn test_handle_toggle_gaps() {
+    let mut bsp = BSPLayout::new();
+    bsp.user_cmd("--toggle-gaps".to_string(), None, "").unwrap();
+    assert!(!defaults(&bsp).gaps_enabled);
+    let layout = bsp.generate_layout(2, 1920, 1080, 1, "").unwrap();
+    assert_eq!(
+        geometry(&layout),
+        vec![(0, 0, 960, 1080), (960, 0, 960, 1080)]
+    );
+
+    // The gaps keep their sizes while they are off, and can still be changed
+    assert_eq!(
+        (defaults(&bsp).ig_left, defaults(&bsp).og_left),
+        (Gap::Px(5), Gap::Px(10))
+    );
+    bsp.user_cmd("--inc-outer-gap 10".to_string(), None, "")
+        .unwrap();
+    let layout = bsp.generate_layout(2, 1920, 1080, 1, "").unwrap();
+    assert_eq!(layout.views.first().unwrap().x, 0);
+
+    bsp.user_cmd("--toggle-gaps".to_string(), None, "").unwrap();
+    assert!(defaults(&bsp).gaps_enabled);
+    let mut expected = BSPLayout::new();
+    expected.set_all_outer_gaps(20);
+    assert_eq!(
+        geometry(&bsp.generate_layout(2, 1920, 1080, 1, "").unwrap()),
+        geometry(&expected.generate_layout(2, 1920, 1080, 1, "").unwrap())
     );
+
+    // Each output and tags toggles its own gaps
+    bsp.user_cmd("--toggle-gaps".to_string(), Some(1), "eDP-1")
+        .unwrap();
+    assert!(!bsp.state("eDP-1", 1).unwrap().gaps_enabled);
+    assert!(defaults(&bsp).gaps_enabled);
+}
+
+#[test]
+fn test_handle_gap_scale() {
+    let mut bsp = BSPLayout::new();
+    bsp.user_cmd("--gap-scale 1.0,0.75,0.5@6".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).gap_scale, "1.0,0.75,0.5@6".parse().unwrap());
+
+    // A scale that cannot be parsed leaves the previous one
+    bsp.user_cmd("--gap-scale 1.0,0.5@1".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).gap_scale.factor(6), 0.5);
+
+    bsp.user_cmd("--gap-scale=".to_string(), None, "").unwrap();
+    assert_eq!(defaults(&bsp).gap_scale.factor(6), 1.0);
 }
 
 #[test]
@@ -101,10 +339,10 @@ fn test_handle_start_split() {
     bsp.start_hsplit = false;
     bsp.user_cmd("--start-hsplit".to_string(), None, "")
         .unwrap();
-    assert!(bsp.start_hsplit);
+    assert!(defaults(&bsp).start_hsplit);
     bsp.user_cmd("--start-vsplit".to_string(), None, "")
         .unwrap();
-    assert!(!bsp.start_hsplit);
+    assert!(!defaults(&bsp).start_hsplit);
 
     bsp.user_cmd("--start-vsplit --start-hsplit".to_string(), None, "")
         .unwrap_err();
@@ -117,18 +355,18 @@ fn test_handle_set_split() {
     bsp.hsplit_perc = 0.5;
     bsp.user_cmd("--split-perc 0.6".to_string(), None, "")
         .unwrap();
-    assert_eq!(bsp.vsplit_perc, 0.6);
-    assert_eq!(bsp.hsplit_perc, 0.6);
+    assert_eq!(defaults(&bsp).vsplit_perc, 0.6);
+    assert_eq!(defaults(&bsp).hsplit_perc, 0.6);
 
     bsp.user_cmd("--vsplit-perc 0.4".to_string(), None, "")
         .unwrap();
-    assert_eq!(bsp.vsplit_perc, 0.4);
-    assert_eq!(bsp.hsplit_perc, 0.6);
+    assert_eq!(defaults(&bsp).vsplit_perc, 0.4);
+    assert_eq!(defaults(&bsp).hsplit_perc, 0.6);
 
     bsp.user_cmd("--hsplit-perc 0.3".to_string(), None, "")
         .unwrap();
-    assert_eq!(bsp.vsplit_perc, 0.4);
-    assert_eq!(bsp.hsplit_perc, 0.3);
+    assert_eq!(defaults(&bsp).vsplit_perc, 0.4);
+    assert_eq!(defaults(&bsp).hsplit_perc, 0.3);
 
     bsp.user_cmd(
         "--split-perc 0.5 --hsplit-perc 0.2 --vsplit-perc 0.1".to_string(),
@@ -136,8 +374,8 @@ fn test_handle_set_split() {
         "",
     )
     .unwrap();
-    assert_eq!(bsp.vsplit_perc, 0.1);
-    assert_eq!(bsp.hsplit_perc, 0.2);
+    assert_eq!(defaults(&bsp).vsplit_perc, 0.1);
+    assert_eq!(defaults(&bsp).hsplit_perc, 0.2);
 }
 
 #[test]
@@ -146,24 +384,184 @@ fn test_handle_ch_split() {
     bsp.vsplit_perc = 0.5;
     bsp.user_cmd("--inc-vsplit 0.3".to_string(), None, "")
         .unwrap();
-    assert_eq!(bsp.vsplit_perc, 0.8);
+    assert_eq!(defaults(&bsp).vsplit_perc, 0.8);
 
     bsp.user_cmd("--dec-vsplit 0.3".to_string(), None, "")
         .unwrap();
-    assert_eq!(bsp.vsplit_perc, 0.5);
+    assert_eq!(defaults(&bsp).vsplit_perc, 0.5);
 
     bsp.hsplit_perc = 0.5;
     bsp.user_cmd("--inc-hsplit 0.3".to_string(), None, "")
         .unwrap();
-    assert_eq!(bsp.hsplit_perc, 0.8);
+    assert_eq!(defaults(&bsp).hsplit_perc, 0.8);
 
     bsp.user_cmd("--dec-hsplit 0.3".to_string(), None, "")
         .unwrap();
-    assert_eq!(bsp.hsplit_perc, 0.5);
+    assert_eq!(defaults(&bsp).hsplit_perc, 0.5);
 
     bsp.user_cmd("--inc-hsplit 0.3 --inc-vsplit 0.3".to_string(), None, "")
         .unwrap();
-    assert_eq!((bsp.hsplit_perc, bsp.vsplit_perc), (0.8, 0.8));
+    assert_eq!(
+        (defaults(&bsp).hsplit_perc, defaults(&bsp).vsplit_perc),
+        (0.8, 0.8)
+    );
+
+    // Negative amounts stay on the screen too
+    bsp.user_cmd("--inc-vsplit=-2 --dec-hsplit=-2".to_string(), None, "")
+        .unwrap();
+    assert_eq!(
+        (defaults(&bsp).hsplit_perc, defaults(&bsp).vsplit_perc),
+        (0.9999, 0.0001)
+    );
+}
+
+#[test]
+fn test_handle_deeper_split() {
+    let mut bsp = BSPLayout::new();
+    bsp.user_cmd("--vsplit-perc 0.625,0.5".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).vsplit_perc, 0.625);
+    assert_eq!(defaults(&bsp).deeper_vsplit_perc, vec![0.5]);
+    assert!(defaults(&bsp).deeper_hsplit_perc.is_empty());
+
+    // The levels below the changed one keep the percentage they had
+    bsp.user_cmd("--inc-vsplit 0.25 --depth 2".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).vsplit_perc, 0.625);
+    assert_eq!(defaults(&bsp).deeper_vsplit_perc, vec![0.5, 0.75, 0.5]);
+
+    bsp.user_cmd("--dec-vsplit 0.25".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).vsplit_perc, 0.375);
+    assert_eq!(defaults(&bsp).deeper_vsplit_perc, vec![0.25, 0.5, 0.25]);
+
+    bsp.user_cmd("--inc-hsplit 0.25 --depth 0".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).hsplit_perc, 0.75);
+    assert_eq!(defaults(&bsp).deeper_hsplit_perc, vec![0.5]);
+
+    bsp.user_cmd("--split-perc 0.5".to_string(), None, "")
+        .unwrap();
+    assert_eq!(
+        (defaults(&bsp).hsplit_perc, defaults(&bsp).vsplit_perc),
+        (0.5, 0.5)
+    );
+    assert!(defaults(&bsp).deeper_hsplit_perc.is_empty());
+    assert!(defaults(&bsp).deeper_vsplit_perc.is_empty());
+
+    // Nothing changes if any of the percentages is out of range
+    for cmd in [
+        "--vsplit-perc 0.625,1.5",
+        "--hsplit-perc 0",
+        "--split-perc 0.25 --vsplit-perc 0.75,-0.5",
+    ] {
+        assert!(bsp.user_cmd(cmd.to_string(), None, "").is_err());
+    }
+    assert_eq!(
+        (defaults(&bsp).hsplit_perc, defaults(&bsp).vsplit_perc),
+        (0.5, 0.5)
+    );
+    assert!(defaults(&bsp).deeper_vsplit_perc.is_empty());
+}
+
+#[test]
+fn test_handle_split_decay() {
+    let mut bsp = BSPLayout::new();
+    assert_eq!(bsp.split_decay, 1.0);
+    bsp.user_cmd("--split-decay 0.5".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).split_decay, 0.5);
+
+    assert!(bsp
+        .user_cmd("--split-decay 1.5".to_string(), None, "")
+        .is_err());
+    assert_eq!(defaults(&bsp).split_decay, 0.5);
+}
+
+#[test]
+fn test_handle_split_px() {
+    let mut bsp = BSPLayout::new();
+    bsp.user_cmd("--hsplit-px 300".to_string(), None, "eDP-1")
+        .unwrap();
+    let defaults = bsp.output_state("eDP-1").unwrap();
+    assert_eq!((defaults.hsplit_px, defaults.vsplit_px), (300, 0));
+
+    // Starts from the size of the split in the last layout, inside the outer gaps
+    bsp.generate_layout(2, 1366, 768, 1, "eDP-1").unwrap();
+    bsp.user_cmd("--inc-vsplit-px 50".to_string(), Some(1), "eDP-1")
+        .unwrap();
+    assert_eq!(bsp.state("eDP-1", 1).unwrap().vsplit_px, 723);
+    assert_eq!(bsp.output_state("eDP-1").unwrap().vsplit_px, 0);
+
+    bsp.user_cmd("--dec-vsplit-px 1000".to_string(), Some(1), "eDP-1")
+        .unwrap();
+    assert_eq!(bsp.state("eDP-1", 1).unwrap().vsplit_px, 1);
+
+    bsp.user_cmd(
+        "--vsplit-px 0 --dec-hsplit-px 100".to_string(),
+        Some(1),
+        "eDP-1",
+    )
+    .unwrap();
+    let state = bsp.state("eDP-1", 1).unwrap();
+    assert_eq!((state.hsplit_px, state.vsplit_px), (200, 0));
+}
+
+#[test]
+fn test_split_px_from_equal_area() {
+    let mut bsp = BSPLayout::new();
+    bsp.equal_area = true;
+    let layout = bsp.generate_layout(3, 1920, 1080, 1, "eDP-1").unwrap();
+    assert_eq!(layout.views.first().unwrap().width, 628);
+
+    // The split grows from the size it had, rather than from the split percentage
+    bsp.user_cmd("--inc-vsplit-px 10".to_string(), Some(1), "eDP-1")
+        .unwrap();
+    assert_eq!(bsp.state("eDP-1", 1).unwrap().vsplit_px, 643);
+    let layout = bsp.generate_layout(3, 1920, 1080, 1, "eDP-1").unwrap();
+    assert_eq!(layout.views.first().unwrap().width, 638);
+
+    // The first horizontal split is inside the primary side of the vertical one
+    bsp.user_cmd("--split-perc 0.75".to_string(), Some(2), "eDP-1")
+        .unwrap();
+    bsp.generate_layout(4, 1920, 1080, 2, "eDP-1").unwrap();
+    bsp.user_cmd("--inc-hsplit-px 10".to_string(), Some(2), "eDP-1")
+        .unwrap();
+    assert_eq!(bsp.state("eDP-1", 2).unwrap().hsplit_px, 805);
+    let layout = bsp.generate_layout(4, 1920, 1080, 2, "eDP-1").unwrap();
+    assert_eq!(layout.views.first().unwrap().height, 800);
+}
+
+#[test]
+fn test_handle_smart_gaps() {
+    let mut bsp = BSPLayout::new();
+    bsp.user_cmd("--smart-gaps".to_string(), Some(1), "eDP-1")
+        .unwrap();
+    assert_eq!(bsp.state("eDP-1", 1).unwrap().smart_gaps, SmartGaps::Single);
+    assert_eq!(bsp.smart_gaps, SmartGaps::Off);
+
+    // Only the focused tags lose their gaps
+    let layout = bsp.generate_layout(1, 1920, 1080, 1, "eDP-1").unwrap();
+    assert_eq!(layout.views.first().unwrap().x, 0);
+    let layout = bsp.generate_layout(1, 1920, 1080, 2, "eDP-1").unwrap();
+    assert_eq!(layout.views.first().unwrap().x, 10);
+
+    bsp.user_cmd("--smart-gaps stacked".to_string(), Some(1), "eDP-1")
+        .unwrap();
+    assert_eq!(
+        bsp.state("eDP-1", 1).unwrap().smart_gaps,
+        SmartGaps::Stacked
+    );
+
+    bsp.user_cmd("--toggle-smart-gaps".to_string(), Some(1), "eDP-1")
+        .unwrap();
+    assert_eq!(bsp.state("eDP-1", 1).unwrap().smart_gaps, SmartGaps::Off);
+    bsp.user_cmd("--toggle-smart-gaps".to_string(), Some(1), "eDP-1")
+        .unwrap();
+    assert_eq!(
+        bsp.state("eDP-1", 1).unwrap().smart_gaps,
+        SmartGaps::Stacked
+    );
 }
 
 #[test]
@@ -172,7 +570,524 @@ fn test_handle_reverse() {
     bsp.reversed = false;
 
     bsp.user_cmd("--reverse".to_string(), None, "").unwrap();
-    assert!(bsp.reversed);
+    assert!(defaults(&bsp).reversed);
     bsp.user_cmd("--reverse".to_string(), None, "").unwrap();
+    assert!(!defaults(&bsp).reversed);
+}
+
+#[test]
+fn test_user_cmd_per_tag() {
+    let mut bsp = BSPLayout::new();
+    bsp.vsplit_perc = 0.5;
+    bsp.user_cmd("--ig-top 1".to_string(), Some(0b10000), "")
+        .unwrap();
+
+    bsp.user_cmd("--inc-vsplit 0.1 --reverse".to_string(), Some(1), "")
+        .unwrap();
+    let first_tag = bsp.state("", 1).unwrap();
+    assert_eq!(first_tag.vsplit_perc, 0.6);
+    assert!(first_tag.reversed);
+
+    let fifth_tag = bsp.state("", 0b10000).unwrap();
+    assert_eq!(fifth_tag.vsplit_perc, 0.5);
+    assert!(!fifth_tag.reversed);
+    assert_eq!(bsp.vsplit_perc, 0.5);
     assert!(!bsp.reversed);
 }
+
+#[test]
+fn test_user_cmd_without_tags() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_outer_gaps(0);
+    bsp.user_cmd("--reverse".to_string(), Some(1), "").unwrap();
+    assert!(bsp.state("", 2).is_none());
+
+    bsp.user_cmd("--reverse".to_string(), Some(1), "DP-2")
+        .unwrap();
+
+    bsp.user_cmd("--outer-gap 5".to_string(), None, "").unwrap();
+    assert_eq!(defaults(&bsp).og_top, Gap::Px(5));
+    assert_eq!(bsp.state("", 1).unwrap().og_top, Gap::Px(5));
+
+    // New tag states are created from the updated defaults of their output
+    bsp.user_cmd("--reverse".to_string(), Some(2), "").unwrap();
+    assert_eq!(bsp.state("", 2).unwrap().og_top, Gap::Px(5));
+
+    // Other outputs keep their states and defaults
+    assert_eq!(bsp.state("DP-2", 1).unwrap().og_top, Gap::Px(0));
+    assert!(bsp.output_state("DP-2").is_none());
+    assert_eq!(bsp.og_top, Gap::Px(0));
+    bsp.user_cmd("--reverse".to_string(), Some(2), "DP-2")
+        .unwrap();
+    assert_eq!(bsp.state("DP-2", 2).unwrap().og_top, Gap::Px(0));
+}
+
+#[test]
+fn test_user_cmd_per_output() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_outer_gaps(0);
+    bsp.user_cmd("--outer-gap 20 --start-hsplit".to_string(), Some(1), "DP-2")
+        .unwrap();
+    bsp.user_cmd("--inc-hsplit 0.1".to_string(), Some(1), "eDP-1")
+        .unwrap();
+
+    let external = bsp.state("DP-2", 1).unwrap();
+    assert_eq!(
+        (external.og_top, external.og_left),
+        (Gap::Px(20), Gap::Px(20))
+    );
+    assert!(external.start_hsplit);
+    assert_eq!(external.hsplit_perc, 0.5);
+
+    let laptop = bsp.state("eDP-1", 1).unwrap();
+    assert_eq!((laptop.og_top, laptop.og_left), (Gap::Px(0), Gap::Px(0)));
+    assert!(!laptop.start_hsplit);
+    assert_eq!(laptop.hsplit_perc, 0.6);
+}
+
+#[test]
+fn test_handle_equal_area() {
+    let mut bsp = BSPLayout::new();
+    bsp.equal_area = false;
+
+    bsp.user_cmd("--equal-area".to_string(), None, "").unwrap();
+    assert!(defaults(&bsp).equal_area);
+    bsp.user_cmd("--equal-area".to_string(), None, "").unwrap();
+    assert!(!defaults(&bsp).equal_area);
+}
+
+#[test]
+fn test_handle_split_direction() {
+    let mut bsp = BSPLayout::new();
+    bsp.user_cmd("--split-direction longest-side".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).split_direction, SplitDirection::LongestSide);
+
+    bsp.user_cmd("--split-bias 1.5".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).split_bias, 1.5);
+    bsp.user_cmd("--split-bias 0".to_string(), None, "")
+        .unwrap_err();
+    assert_eq!(defaults(&bsp).split_bias, 1.5);
+
+    bsp.user_cmd("--split-direction alternate".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).split_direction, SplitDirection::Alternate);
+}
+
+#[test]
+fn test_handle_layout() {
+    let mut bsp = BSPLayout::new();
+    bsp.user_cmd("--layout master-stack".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).mode, LayoutMode::MasterStack);
+    bsp.user_cmd("--layout bsp".to_string(), None, "").unwrap();
+    assert_eq!(defaults(&bsp).mode, LayoutMode::Bsp);
+}
+
+#[test]
+fn test_handle_main() {
+    let mut bsp = BSPLayout::new();
+    bsp.user_cmd("--main-count 3 --main-location top".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).main_count, 3);
+    assert_eq!(defaults(&bsp).main_location, MainLocation::Top);
+    bsp.user_cmd("--inc-main-count 1".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).main_count, 4);
+    bsp.user_cmd("--dec-main-count 10".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).main_count, 0);
+
+    bsp.user_cmd("--main-ratio 0.5".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).main_ratio, 0.5);
+    bsp.user_cmd("--main-ratio 1.0".to_string(), None, "")
+        .unwrap_err();
+    assert_eq!(defaults(&bsp).main_ratio, 0.5);
+    bsp.user_cmd("--inc-main-ratio 0.6".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).main_ratio, 0.9999);
+    bsp.user_cmd("--dec-main-ratio 1.0".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).main_ratio, 0.0001);
+}
+
+#[test]
+fn test_handle_toggle_monocle() {
+    let mut bsp = BSPLayout::new();
+    bsp.user_cmd("--layout master-stack".to_string(), None, "")
+        .unwrap();
+    bsp.user_cmd("--toggle-monocle".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).mode, LayoutMode::Monocle);
+    bsp.user_cmd("--toggle-monocle".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).mode, LayoutMode::MasterStack);
+
+    // Switching to monocle directly also remembers the previous mode
+    bsp.user_cmd("--layout bsp".to_string(), None, "").unwrap();
+    bsp.user_cmd("--layout monocle".to_string(), None, "")
+        .unwrap();
+    bsp.user_cmd("--toggle-monocle".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).mode, LayoutMode::Bsp);
+
+    // Each tag toggles on its own
+    bsp.user_cmd("--toggle-monocle".to_string(), Some(1), "eDP-1")
+        .unwrap();
+    assert_eq!(bsp.state("eDP-1", 1).unwrap().mode, LayoutMode::Monocle);
+    assert_eq!(defaults(&bsp).mode, LayoutMode::Bsp);
+}
+
+#[test]
+fn test_handle_grid() {
+    let mut bsp = BSPLayout::new();
+    bsp.user_cmd(
+        "--layout grid --grid-cols 3 --grid-rows 2 --grid-fill center".to_string(),
+        None,
+        "",
+    )
+    .unwrap();
+    assert_eq!(defaults(&bsp).mode, LayoutMode::Grid);
+    assert_eq!((defaults(&bsp).grid_cols, defaults(&bsp).grid_rows), (3, 2));
+    assert_eq!(defaults(&bsp).grid_fill, GridFill::Center);
+}
+
+#[test]
+fn test_handle_stack_sizing() {
+    let mut bsp = BSPLayout::new();
+    bsp.user_cmd(
+        "--layout columns --stack-sizing geometric".to_string(),
+        None,
+        "",
+    )
+    .unwrap();
+    assert_eq!(defaults(&bsp).mode, LayoutMode::Columns);
+    assert_eq!(defaults(&bsp).stack_sizing, StackSizing::Geometric);
+}
+
+#[test]
+fn test_handle_cycle_layout() {
+    let mut bsp = BSPLayout::new();
+    bsp.user_cmd("--layout-cycle bsp,monocle,grid".to_string(), None, "")
+        .unwrap();
+    assert_eq!(
+        defaults(&bsp).layout_cycle,
+        vec![LayoutMode::Bsp, LayoutMode::Monocle, LayoutMode::Grid]
+    );
+
+    bsp.user_cmd("--next-layout".to_string(), None, "").unwrap();
+    assert_eq!(defaults(&bsp).mode, LayoutMode::Monocle);
+    bsp.user_cmd("--prev-layout".to_string(), None, "").unwrap();
+    bsp.user_cmd("--prev-layout".to_string(), None, "").unwrap();
+    assert_eq!(defaults(&bsp).mode, LayoutMode::Grid);
+    bsp.user_cmd("--next-layout".to_string(), None, "").unwrap();
+    assert_eq!(defaults(&bsp).mode, LayoutMode::Bsp);
+
+    // A mode outside of the cycle goes to the start of it
+    bsp.user_cmd("--layout rows --next-layout".to_string(), None, "")
+        .unwrap();
+    assert_eq!(defaults(&bsp).mode, LayoutMode::Bsp);
+
+    // Each tag keeps its own mode
+    bsp.user_cmd("--next-layout".to_string(), Some(2), "")
+        .unwrap();
+    assert_eq!(bsp.state("", 2).unwrap().mode, LayoutMode::Monocle);
+    assert_eq!(defaults(&bsp).mode, LayoutMode::Bsp);
+}
+
+#[test]
+fn test_handle_name_format() {
+    let mut bsp = BSPLayout::new();
+    bsp.user_cmd("--name-format {mode}:{count}".to_string(), Some(1), "eDP-1")
+        .unwrap();
+    assert_eq!(bsp.state("eDP-1", 1).unwrap().name_format, "{mode}:{count}");
+    assert_eq!(bsp.name_format, "{mode}");
+
+    // Formats with spaces can be quoted or escaped
+    for (cmd, format) in [
+        ("--name-format '{mode} {count}'", "{mode} {count}"),
+        (
+            "--reverse  --name-format \"{mode} {count} [{vsplit}%] {reversed}\"",
+            "{mode} {count} [{vsplit}%] {reversed}",
+        ),
+        (
+            "--name-format {mode}\\ \\\"{count}\\\"",
+            "{mode} \"{count}\"",
+        ),
+        ("--name-format '{mode} \\ {count}'", "{mode} \\ {count}"),
+        (
+            "--name-format '{mode} {count} [{vsplit}%] {reversed}'",
+            "{mode} {count} [{vsplit}%] {reversed}",
+        ),
+    ] {
+        bsp.user_cmd(cmd.to_string(), Some(2), "eDP-1").unwrap();
+        assert_eq!(bsp.state("eDP-1", 2).unwrap().name_format, format);
+    }
+    let layout = bsp.generate_layout(3, 1920, 1080, 2, "eDP-1").unwrap();
+    assert_eq!(layout.layout_name, "bsp 3 [50%] ⇄");
+}
+
+#[test]
+fn test_handle_node() {
+    let mut bsp = BSPLayout::new();
+
+    // Views can only be found once a layout was made for the tags
+    bsp.user_cmd(
+        "--node-view 3 --node-ratio 0.25".to_string(),
+        Some(1),
+        "eDP-1",
+    )
+    .unwrap_err();
+    bsp.generate_layout(4, 1920, 1080, 1, "eDP-1").unwrap();
+
+    bsp.user_cmd(
+        "--node-view 3 --node-ratio 0.25".to_string(),
+        Some(1),
+        "eDP-1",
+    )
+    .unwrap();
+    let state = bsp.state("eDP-1", 1).unwrap();
+    assert_eq!(
+        state.split_tree.get(&[Side::Secondary]).unwrap().ratio,
+        Some(0.25)
+    );
+    assert_eq!(state.split_tree.ratio, None);
+
+    // Splits without a ratio start from the split percentage
+    bsp.user_cmd(
+        "--node-path / --inc-node-ratio 0.25".to_string(),
+        Some(1),
+        "eDP-1",
+    )
+    .unwrap();
+    assert_eq!(bsp.state("eDP-1", 1).unwrap().split_tree.ratio, Some(0.75));
+    bsp.user_cmd(
+        "--node-path / --dec-node-ratio 1".to_string(),
+        Some(1),
+        "eDP-1",
+    )
+    .unwrap();
+    assert_eq!(
+        bsp.state("eDP-1", 1).unwrap().split_tree.ratio,
+        Some(0.0001)
+    );
+
+    bsp.user_cmd("--node-path / --reset-node".to_string(), Some(1), "eDP-1")
+        .unwrap();
+    let state = bsp.state("eDP-1", 1).unwrap();
+    assert_eq!(state.split_tree.ratio, None);
+    assert_eq!(
+        state.split_tree.get(&[Side::Secondary]).unwrap().ratio,
+        Some(0.25)
+    );
+
+    bsp.user_cmd("--reset-tree".to_string(), Some(1), "eDP-1")
+        .unwrap();
+    assert_eq!(
+        bsp.state("eDP-1", 1).unwrap().split_tree,
+        Default::default()
+    );
+
+    // Splits that do not exist with four views, or ratios out of range
+    bsp.user_cmd(
+        "--node-path /p/p --node-ratio 0.5".to_string(),
+        Some(1),
+        "eDP-1",
+    )
+    .unwrap_err();
+    bsp.user_cmd(
+        "--node-path /x --node-ratio 0.5".to_string(),
+        Some(1),
+        "eDP-1",
+    )
+    .unwrap_err();
+    bsp.user_cmd(
+        "--node-view 4 --node-ratio 0.5".to_string(),
+        Some(1),
+        "eDP-1",
+    )
+    .unwrap_err();
+    bsp.user_cmd(
+        "--node-path / --node-ratio 1.5".to_string(),
+        Some(1),
+        "eDP-1",
+    )
+    .unwrap_err();
+    bsp.user_cmd("--node-ratio 0.5".to_string(), Some(1), "eDP-1")
+        .unwrap_err();
+}
+
+#[test]
+fn test_handle_node_longest_side() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_inner_gaps(0);
+    bsp.set_all_outer_gaps(0);
+    bsp.split_direction = SplitDirection::LongestSide;
+    bsp.hsplit_perc = 0.75;
+    bsp.vsplit_perc = 0.25;
+
+    // The first split of a portrait output is horizontal, even though splits start vertical
+    bsp.generate_layout(2, 1080, 1920, 1, "DP-2").unwrap();
+    bsp.user_cmd(
+        "--node-path / --inc-node-ratio 0.125".to_string(),
+        Some(1),
+        "DP-2",
+    )
+    .unwrap();
+    assert_eq!(bsp.state("DP-2", 1).unwrap().split_tree.ratio, Some(0.875));
+    let layout = bsp.generate_layout(2, 1080, 1920, 1, "DP-2").unwrap();
+    assert_eq!(layout.views.first().unwrap().height, 1680);
+
+    // The ratio of a split that was never used is not known without a layout
+    bsp.user_cmd(
+        "--node-path / --inc-node-ratio 0.125".to_string(),
+        Some(2),
+        "DP-2",
+    )
+    .unwrap_err();
+}
+
+#[test]
+fn test_node_ratio_from_split_px() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_inner_gaps(0);
+    bsp.set_all_outer_gaps(0);
+    bsp.vsplit_px = 1200;
+    bsp.generate_layout(2, 1920, 1080, 1, "eDP-1").unwrap();
+
+    // The split starts from the ratio that keeps it at 1200 pixels
+    bsp.user_cmd(
+        "--grow-view 0 --direction right --by 0.125".to_string(),
+        Some(1),
+        "eDP-1",
+    )
+    .unwrap();
+    let layout = bsp.generate_layout(2, 1920, 1080, 1, "eDP-1").unwrap();
+    assert_eq!(layout.views.first().unwrap().width, 1440);
+
+    // With equal areas, the ratio is the one that equal areas turn into 1200 pixels
+    bsp.equal_area = true;
+    bsp.user_cmd(
+        "--node-path / --inc-node-ratio 0.0".to_string(),
+        Some(1),
+        "eDP-2",
+    )
+    .unwrap_err();
+    bsp.generate_layout(3, 1920, 1080, 1, "eDP-2").unwrap();
+    bsp.user_cmd(
+        "--node-path / --inc-node-ratio 0.0".to_string(),
+        Some(1),
+        "eDP-2",
+    )
+    .unwrap();
+    let layout = bsp.generate_layout(3, 1920, 1080, 1, "eDP-2").unwrap();
+    assert_eq!(layout.views.first().unwrap().width, 1200);
+}
+
+#[test]
+fn test_handle_grow_view() {
+    let mut bsp = BSPLayout::new();
+    bsp.set_all_inner_gaps(0);
+    bsp.set_all_outer_gaps(0);
+    bsp.user_cmd(
+        "--grow-view 2 --direction left".to_string(),
+        Some(1),
+        "eDP-1",
+    )
+    .unwrap_err();
+    bsp.generate_layout(4, 1920, 1080, 1, "eDP-1").unwrap();
+
+    // The third view is in the top right, so moving its left edge moves the first split
+    bsp.user_cmd(
+        "--grow-view 2 --by 0.125 --direction left".to_string(),
+        Some(1),
+        "eDP-1",
+    )
+    .unwrap();
+    let state = bsp.state("eDP-1", 1).unwrap();
+    assert_eq!(state.split_tree.ratio, Some(0.375));
+    assert!(state.split_tree.secondary.is_none());
+
+    // Moving its bottom edge only moves the split between the two views on the right
+    bsp.user_cmd(
+        "--grow-view 2 --by 0.25 --direction down".to_string(),
+        Some(1),
+        "eDP-1",
+    )
+    .unwrap();
+    let state = bsp.state("eDP-1", 1).unwrap();
+    assert_eq!(
+        state.split_tree.get(&[Side::Secondary]).unwrap().ratio,
+        Some(0.75)
+    );
+    assert!(state.split_tree.primary.is_none());
+
+    bsp.user_cmd(
+        "--shrink-view 0 --by 0.125 --direction right".to_string(),
+        Some(1),
+        "eDP-1",
+    )
+    .unwrap();
+    assert_eq!(bsp.state("eDP-1", 1).unwrap().split_tree.ratio, Some(0.25));
+
+    let layout = bsp.generate_layout(4, 1920, 1080, 1, "eDP-1").unwrap();
+    let cells: Vec<_> = layout
+        .views
+        .iter()
+        .map(|v| (v.x, v.y, v.width, v.height))
+        .collect();
+    assert_eq!(
+        cells,
+        vec![
+            (0, 0, 480, 540),
+            (0, 540, 480, 540),
+            (480, 0, 1440, 810),
+            (480, 810, 1440, 270),
+        ]
+    );
+
+    // Nothing is to the right of the third view, and a direction is always needed
+    bsp.user_cmd(
+        "--grow-view 2 --direction right".to_string(),
+        Some(1),
+        "eDP-1",
+    )
+    .unwrap_err();
+    bsp.user_cmd("--grow-view 2".to_string(), Some(1), "eDP-1")
+        .unwrap_err();
+}
+
+#[test]
+fn test_cmd_after_degenerate_layout() {
+    let mut bsp = BSPLayout::new();
+    bsp.generate_layout(0, 1920, 1080, 1, "eDP-1").unwrap();
+    bsp.user_cmd(
+        "--grow-view 0 --direction left".to_string(),
+        Some(1),
+        "eDP-1",
+    )
+    .unwrap_err();
+    bsp.user_cmd(
+        "--node-view 0 --node-ratio 0.6".to_string(),
+        Some(1),
+        "eDP-1",
+    )
+    .unwrap_err();
+
+    // The size of a split is not known without a layout for the tags
+    bsp.user_cmd("--inc-vsplit-px 50".to_string(), None, "")
+        .unwrap_err();
+    bsp.user_cmd("--dec-vsplit-px 50".to_string(), Some(2), "eDP-1")
+        .unwrap_err();
+    assert_eq!(bsp.vsplit_px, 0);
+
+    // Outer gaps that take up the whole output leave a split of 1 pixel to start from
+    bsp.set_all_outer_gaps(1000);
+    bsp.generate_layout(2, 1920, 1080, 3, "eDP-1").unwrap();
+    bsp.user_cmd("--inc-vsplit-px 10".to_string(), Some(3), "eDP-1")
+        .unwrap();
+    assert_eq!(bsp.state("eDP-1", 3).unwrap().vsplit_px, 10);
+    bsp.generate_layout(2, 1920, 1080, 3, "eDP-1").unwrap();
+}